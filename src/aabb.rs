@@ -0,0 +1,60 @@
+use crate::ray::Ray;
+use crate::vector3::Point3;
+
+/// Axis-aligned bounding box.
+#[derive(Debug, Clone, Copy)]
+pub struct Aabb {
+    pub min: Point3,
+    pub max: Point3,
+}
+
+impl Aabb {
+    pub fn new(a: Point3, b: Point3) -> Self {
+        Aabb {
+            min: Point3::new(a.x.min(b.x), a.y.min(b.y), a.z.min(b.z)),
+            max: Point3::new(a.x.max(b.x), a.y.max(b.y), a.z.max(b.z)),
+        }
+    }
+
+    /// Smallest box enclosing both boxes.
+    pub fn union(&self, other: &Aabb) -> Self {
+        Aabb {
+            min: Point3::new(self.min.x.min(other.min.x), self.min.y.min(other.min.y), self.min.z.min(other.min.z)),
+            max: Point3::new(self.max.x.max(other.max.x), self.max.y.max(other.max.y), self.max.z.max(other.max.z)),
+        }
+    }
+
+    pub fn center(&self) -> Point3 {
+        self.min.add(&self.max).mul(0.5)
+    }
+
+    pub fn size(&self) -> Point3 {
+        self.max.sub(&self.min)
+    }
+
+    /// Parametric interval where the ray is inside the box, clipped to
+    /// `[ray_tmin, ray_tmax]`.
+    pub fn hit(&self, ray: &Ray, ray_tmin: f64, ray_tmax: f64) -> Option<(f64, f64)> {
+        let mut t_enter = ray_tmin;
+        let mut t_exit = ray_tmax;
+        let axes = [
+            (ray.origin.x, ray.direction.x, self.min.x, self.max.x),
+            (ray.origin.y, ray.direction.y, self.min.y, self.max.y),
+            (ray.origin.z, ray.direction.z, self.min.z, self.max.z),
+        ];
+        for (origin, direction, min, max) in axes {
+            let inverse = 1.0 / direction;
+            let mut t0 = (min - origin) * inverse;
+            let mut t1 = (max - origin) * inverse;
+            if inverse < 0.0 {
+                std::mem::swap(&mut t0, &mut t1);
+            }
+            t_enter = t_enter.max(t0);
+            t_exit = t_exit.min(t1);
+            if t_exit <= t_enter {
+                return None;
+            }
+        }
+        Some((t_enter, t_exit))
+    }
+}
@@ -0,0 +1,107 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use crate::color::Color;
+
+/// Settings for spending the sample budget where the image is noisiest.
+#[derive(Debug, Clone)]
+pub struct AdaptiveSampling {
+    /// Samples every pixel gets before its variance is trusted.
+    pub min_samples: u32,
+    /// Upper bound on the samples of any single pixel.
+    pub max_samples: u32,
+    /// A pixel stops once the 95% confidence interval of its luminance is
+    /// within this fraction of its mean.
+    pub threshold: f64,
+    /// Where to write a heatmap of the samples taken per pixel.
+    pub heatmap: Option<String>,
+}
+
+impl AdaptiveSampling {
+    pub fn new(min_samples: u32, max_samples: u32, threshold: f64) -> Self {
+        if min_samples < 2 || max_samples < min_samples {
+            panic!("Adaptive sampling needs 2 <= min_samples <= max_samples");
+        }
+        if threshold <= 0.0 {
+            panic!("Threshold must be positive");
+        }
+        AdaptiveSampling {
+            min_samples,
+            max_samples,
+            threshold,
+            heatmap: None,
+        }
+    }
+
+    pub fn with_heatmap(mut self, path: &str) -> Self {
+        self.heatmap = Some(path.to_string());
+        self
+    }
+
+    /// Ratio of the confidence interval half-width to the tolerated error;
+    /// the pixel has converged once this drops to 1.
+    pub fn error(&self, stats: &PixelStats) -> f64 {
+        stats.relative_error() / self.threshold
+    }
+
+    pub fn is_converged(&self, stats: &PixelStats) -> bool {
+        stats.count >= self.max_samples || (stats.count >= self.min_samples && self.error(stats) <= 1.0)
+    }
+}
+
+/// Running mean of a pixel's samples, with Welford's update of the
+/// luminance variance.
+#[derive(Debug, Clone, Copy)]
+pub struct PixelStats {
+    pub count: u32,
+    pub mean: Color,
+    pub mean_luminance: f64,
+    pub m2: f64,
+}
+
+impl PixelStats {
+    pub fn new() -> Self {
+        PixelStats {
+            count: 0,
+            mean: Color::new(0.0, 0.0, 0.0),
+            mean_luminance: 0.0,
+            m2: 0.0,
+        }
+    }
+
+    /// Half-width of the 95% confidence interval of the luminance relative
+    /// to its mean. Dark pixels are judged against a floor so they are not
+    /// chased into extreme sample counts.
+    pub fn relative_error(&self) -> f64 {
+        if self.count < 2 {
+            return f64::INFINITY;
+        }
+        let variance = self.m2 / (self.count - 1) as f64;
+        let half_width = 1.96 * (variance / self.count as f64).sqrt();
+        half_width / self.mean_luminance.max(0.01)
+    }
+
+    pub fn add(&mut self, sample: &Color) {
+        self.count += 1;
+        let n = self.count as f64;
+        self.mean = self.mean.add(&sample.sub(&self.mean).div(n));
+        let luminance = sample.luminance();
+        let delta = luminance - self.mean_luminance;
+        self.mean_luminance += delta / n;
+        self.m2 += delta * (luminance - self.mean_luminance);
+    }
+}
+
+/// Write sample counts as a PPM image running from blue (fewest) to red
+/// (most samples).
+pub fn write_heatmap(path: &str, width: u32, height: u32, counts: &[u32]) -> Result<(), std::io::Error> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    writeln!(writer, "P3\n{} {}\n255", width, height)?;
+    let min = counts.iter().copied().min().unwrap_or(0) as f64;
+    let max = counts.iter().copied().max().unwrap_or(0) as f64;
+    for &count in counts {
+        let t = if max > min { (count as f64 - min) / (max - min) } else { 0.0 };
+        let to_byte = |value: f64| (255.0 * value.clamp(0.0, 1.0)) as u8;
+        writeln!(writer, "{} {} {}", to_byte(t), to_byte(4.0 * t * (1.0 - t)), to_byte(1.0 - t))?;
+    }
+    Ok(())
+}
@@ -0,0 +1,33 @@
+use crate::color::Color;
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::texture::Texture;
+use crate::hittable::HitRecord;
+
+/// Cuts a base material out with an opacity texture, for foliage cards and
+/// decals. The red channel of the texture is the coverage.
+pub struct AlphaMasked {
+    base: Box<dyn Material>,
+    opacity: Box<dyn Texture>,
+}
+
+impl AlphaMasked {
+    pub fn new(base: Box<dyn Material>, opacity: Box<dyn Texture>) -> Self {
+        AlphaMasked { base, opacity }
+    }
+}
+
+impl Material for AlphaMasked {
+    fn scatter(&self, ray_in: &Ray, hit_record: &HitRecord) -> Option<(Color, Ray)> {
+        self.base.scatter(ray_in, hit_record)
+    }
+
+    fn emitted(&self, hit_record: &HitRecord) -> Color {
+        self.base.emitted(hit_record)
+    }
+
+    fn opacity(&self, hit_record: &HitRecord) -> f64 {
+        let coverage = self.opacity.value(hit_record.u, hit_record.v, &hit_record.point).r();
+        coverage.clamp(0.0, 1.0) * self.base.opacity(hit_record)
+    }
+}
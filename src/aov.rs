@@ -0,0 +1,121 @@
+use std::io::Error;
+use crate::color::Color;
+use crate::exr;
+use crate::film::Film;
+use crate::lpe::LightPathExpression;
+
+/// An extra image layer rendered alongside the beauty. The geometric
+/// layers are taken from the first hit of every camera sample.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Aov {
+    /// Distance from the camera, infinite where nothing was hit.
+    Depth,
+    /// World-space shading normal.
+    Normal,
+    Albedo,
+    /// One plus the index of the object in the scene, 0 for the background.
+    ObjectId,
+    /// Per-material hash, 0 for the background.
+    MaterialId,
+    /// World-space position.
+    Position,
+    /// Light reaching the camera from each light group, plus the
+    /// background; together they add up to the beauty.
+    LightGroups,
+}
+
+/// Layers to write to a multi-layer OpenEXR file next to the image.
+#[derive(Debug, Clone)]
+pub struct Aovs {
+    pub path: String,
+    pub layers: Vec<Aov>,
+    /// Buckets splitting the beauty by the paths light took, each written
+    /// as a layer named after it.
+    pub light_paths: Vec<LightPathExpression>,
+}
+
+impl Aovs {
+    pub fn new(path: &str, layers: &[Aov]) -> Self {
+        Aovs {
+            path: path.to_string(),
+            layers: layers.to_vec(),
+            light_paths: Vec::new(),
+        }
+    }
+
+    pub fn with_light_paths(mut self, light_paths: Vec<LightPathExpression>) -> Self {
+        self.light_paths = light_paths;
+        self
+    }
+
+    pub fn has(&self, aov: Aov) -> bool {
+        self.layers.contains(&aov)
+    }
+
+    /// Write the beauty `pixels` and the requested layers of `film`.
+    /// Film layers named `light_*` are written with the light groups, and
+    /// those named `lpe_*` always.
+    pub fn write(&self, film: &Film, pixels: &[Color]) -> Result<(), Error> {
+        let pixel_count = pixels.len();
+        let mut channels = Vec::new();
+        add_color(&mut channels, "", pixels);
+
+        for aov in &self.layers {
+            match aov {
+                Aov::Depth => {
+                    let depth = (0..pixel_count).map(|k| film.guides(k).2 as f32).collect();
+                    channels.push(("depth.Z".to_string(), depth));
+                }
+                Aov::Normal => {
+                    let normals: Vec<_> = (0..pixel_count).map(|k| film.guides(k).1).collect();
+                    add_vector(&mut channels, "normal", normals.iter().map(|n| [n.x, n.y, n.z]));
+                }
+                Aov::Albedo => {
+                    let albedo: Vec<Color> = (0..pixel_count).map(|k| film.guides(k).0).collect();
+                    add_color(&mut channels, "albedo.", &albedo);
+                }
+                Aov::ObjectId => {
+                    channels.push(("object_id.ID".to_string(), film.object_ids.iter().map(|&id| id as f32).collect()));
+                }
+                Aov::MaterialId => {
+                    channels.push(("material_id.ID".to_string(), film.material_ids.iter().map(|&id| id as f32).collect()));
+                }
+                Aov::Position => {
+                    let positions: Vec<_> = (0..pixel_count).map(|k| film.position(k)).collect();
+                    add_vector(&mut channels, "position", positions.iter().map(|p| [p.x, p.y, p.z]));
+                }
+                Aov::LightGroups => {
+                    for (index, name) in film.layer_names.iter().enumerate() {
+                        if name.starts_with("light_") {
+                            add_color(&mut channels, &format!("{}.", name), &film.resolve_layer(index));
+                        }
+                    }
+                }
+            }
+        }
+        for (index, name) in film.layer_names.iter().enumerate() {
+            if name.starts_with("lpe_") {
+                add_color(&mut channels, &format!("{}.", name), &film.resolve_layer(index));
+            }
+        }
+        exr::write(&self.path, film.width, film.height, &channels)
+    }
+}
+
+fn add_color(channels: &mut Vec<(String, Vec<f32>)>, prefix: &str, colors: &[Color]) {
+    channels.push((format!("{}R", prefix), colors.iter().map(|c| c.r() as f32).collect()));
+    channels.push((format!("{}G", prefix), colors.iter().map(|c| c.g() as f32).collect()));
+    channels.push((format!("{}B", prefix), colors.iter().map(|c| c.b() as f32).collect()));
+}
+
+fn add_vector(channels: &mut Vec<(String, Vec<f32>)>, layer: &str, vectors: impl Iterator<Item = [f64; 3]>) {
+    let mut columns = [Vec::new(), Vec::new(), Vec::new()];
+    for vector in vectors {
+        for (column, value) in columns.iter_mut().zip(vector) {
+            column.push(value as f32);
+        }
+    }
+    for (axis, column) in ["X", "Y", "Z"].iter().zip(columns) {
+        channels.push((format!("{}.{}", layer, axis), column));
+    }
+}
@@ -0,0 +1,114 @@
+use std::f64::consts::PI;
+use std::io::{Error, ErrorKind};
+use crate::sampler;
+use crate::texture::{ImageTexture, Texture};
+use crate::vector3::{Point3, Vector3};
+
+/// Outline of the lens opening, which out-of-focus highlights take on.
+#[derive(Debug)]
+pub enum ApertureShape {
+    Circular,
+    /// Regular polygon formed by `blades` straight blades, rotated by
+    /// `rotation` degrees.
+    Polygonal { blades: u32, rotation: f64 },
+    /// Transmission mask covering the square around the unit disk, with
+    /// the cumulative distribution of its pixels for importance sampling.
+    Image { width: usize, height: usize, cdf: Vec<f64> },
+}
+
+/// Aperture used to sample the defocus disk.
+#[derive(Debug)]
+pub struct Aperture {
+    pub shape: ApertureShape,
+    /// Anamorphic squeeze factor. Values above 1 narrow the aperture
+    /// horizontally, giving the tall oval bokeh of anamorphic lenses.
+    pub squeeze: f64,
+}
+
+impl Aperture {
+    pub fn circular() -> Self {
+        Aperture {
+            shape: ApertureShape::Circular,
+            squeeze: 1.0,
+        }
+    }
+
+    pub fn polygonal(blades: u32, rotation: f64) -> Self {
+        if blades < 3 {
+            panic!("An aperture needs at least 3 blades");
+        }
+        Aperture {
+            shape: ApertureShape::Polygonal { blades, rotation },
+            squeeze: 1.0,
+        }
+    }
+
+    /// Load a mask from a PPM image. Its red channel is the transmission,
+    /// the image spanning the full width and height of the defocus disk.
+    pub fn from_image(path: &str) -> Result<Self, Error> {
+        let mask = ImageTexture::load(path)?;
+        let (width, height) = mask.dimensions();
+        let mut cdf = Vec::with_capacity(width * height);
+        let mut total = 0.0;
+        for j in 0..height {
+            for i in 0..width {
+                let u = (i as f64 + 0.5) / width as f64;
+                let v = 1.0 - (j as f64 + 0.5) / height as f64;
+                total += mask.value(u, v, &Point3::default()).r();
+                cdf.push(total);
+            }
+        }
+        if total <= 0.0 {
+            return Err(Error::new(ErrorKind::InvalidData, format!("{}: aperture mask is fully opaque", path)));
+        }
+        cdf.iter_mut().for_each(|value| *value /= total);
+        Ok(Aperture {
+            shape: ApertureShape::Image { width, height, cdf },
+            squeeze: 1.0,
+        })
+    }
+
+    pub fn with_squeeze(mut self, squeeze: f64) -> Self {
+        if squeeze <= 0.0 {
+            panic!("Anamorphic squeeze must be positive");
+        }
+        self.squeeze = squeeze;
+        self
+    }
+
+    /// Random point on the aperture, within the unit disk (or, for image
+    /// masks, the square around it) in the z = 0 plane. Uses a single 2D
+    /// sample, reusing what is left of the first number after it has
+    /// picked a blade or mask pixel.
+    pub fn sample(&self) -> Vector3 {
+        let p = match &self.shape {
+            ApertureShape::Circular => Vector3::random_in_unit_disk(),
+            ApertureShape::Polygonal { blades, rotation } => {
+                // Pick one of the triangles fanning out from the centre and
+                // sample it uniformly.
+                let (u1, u2) = sampler::get_2d();
+                let scaled = u1 * *blades as f64;
+                let blade = (scaled as u32).min(blades - 1);
+                let sector = 2.0 * PI / *blades as f64;
+                let start = rotation.to_radians() + sector * blade as f64;
+                let a = Vector3::new(start.cos(), start.sin(), 0.0);
+                let b = Vector3::new((start + sector).cos(), (start + sector).sin(), 0.0);
+                let (mut s, mut t) = (scaled - blade as f64, u2);
+                if s + t > 1.0 {
+                    (s, t) = (1.0 - s, 1.0 - t);
+                }
+                a.mul(s).add(&b.mul(t))
+            }
+            ApertureShape::Image { width, height, cdf } => {
+                let (u1, u2) = sampler::get_2d();
+                let index = cdf.partition_point(|&value| value < u1).min(cdf.len() - 1);
+                let low = if index > 0 { cdf[index - 1] } else { 0.0 };
+                let offset = ((u1 - low) / (cdf[index] - low).max(f64::MIN_POSITIVE)).clamp(0.0, 1.0);
+                let u = ((index % width) as f64 + offset) / *width as f64;
+                let v = ((index / width) as f64 + u2) / *height as f64;
+                Vector3::new(2.0 * u - 1.0, 1.0 - 2.0 * v, 0.0)
+            }
+        };
+        Vector3::new(p.x / self.squeeze, p.y, 0.0)
+    }
+}
@@ -0,0 +1,127 @@
+use std::sync::OnceLock;
+
+pub const TILE_SIZE: usize = 64;
+const SIGMA: f64 = 1.5;
+
+/// Value in [0, 1) of the blue-noise tile at `(x, y)`, wrapping around so
+/// the tile repeats seamlessly over the image.
+pub fn value(x: u32, y: u32) -> f64 {
+    let tile = TILE.get_or_init(void_and_cluster);
+    tile[(y as usize % TILE_SIZE) * TILE_SIZE + x as usize % TILE_SIZE]
+}
+
+static TILE: OnceLock<Vec<f64>> = OnceLock::new();
+
+/// Energy of every cell of the tile: the sum of a Gaussian of the toroidal
+/// distance to each point of a binary pattern.
+struct Energy {
+    kernel: Vec<f64>,
+    values: Vec<f64>,
+}
+
+impl Energy {
+    fn new() -> Self {
+        let mut kernel = vec![0.0; TILE_SIZE * TILE_SIZE];
+        for dy in 0..TILE_SIZE {
+            for dx in 0..TILE_SIZE {
+                let wrap = |d: usize| d.min(TILE_SIZE - d) as f64;
+                let distance_squared = wrap(dx).powi(2) + wrap(dy).powi(2);
+                kernel[dy * TILE_SIZE + dx] = (-distance_squared / (2.0 * SIGMA * SIGMA)).exp();
+            }
+        }
+        Energy {
+            kernel,
+            values: vec![0.0; TILE_SIZE * TILE_SIZE],
+        }
+    }
+
+    /// Add (`sign` 1) or remove (`sign` -1) the point at `index`.
+    fn update(&mut self, index: usize, sign: f64) {
+        let (px, py) = (index % TILE_SIZE, index / TILE_SIZE);
+        for y in 0..TILE_SIZE {
+            let dy = (y + TILE_SIZE - py) % TILE_SIZE;
+            for x in 0..TILE_SIZE {
+                let dx = (x + TILE_SIZE - px) % TILE_SIZE;
+                self.values[y * TILE_SIZE + x] += sign * self.kernel[dy * TILE_SIZE + dx];
+            }
+        }
+    }
+
+    /// The set point with the highest energy.
+    fn tightest_cluster(&self, pattern: &[bool]) -> usize {
+        (0..pattern.len())
+            .filter(|&i| pattern[i])
+            .max_by(|&a, &b| self.values[a].total_cmp(&self.values[b]))
+            .unwrap()
+    }
+
+    /// The empty cell with the lowest energy.
+    fn largest_void(&self, pattern: &[bool]) -> usize {
+        (0..pattern.len())
+            .filter(|&i| !pattern[i])
+            .min_by(|&a, &b| self.values[a].total_cmp(&self.values[b]))
+            .unwrap()
+    }
+}
+
+/// Rank every cell with Ulichney's void-and-cluster method and normalize
+/// the ranks to [0, 1).
+fn void_and_cluster() -> Vec<f64> {
+    let cells = TILE_SIZE * TILE_SIZE;
+
+    // Start from a fixed pseudorandom pattern covering a tenth of the tile
+    // and relax it by moving the tightest cluster into the largest void.
+    let mut pattern = vec![false; cells];
+    let mut state = 0x2545_f491_4f6c_dd1du64;
+    let mut ones = 0;
+    while ones < cells / 10 {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        let index = (state % cells as u64) as usize;
+        if !pattern[index] {
+            pattern[index] = true;
+            ones += 1;
+        }
+    }
+    let mut energy = Energy::new();
+    for index in (0..cells).filter(|&i| pattern[i]) {
+        energy.update(index, 1.0);
+    }
+    for _ in 0..cells {
+        let cluster = energy.tightest_cluster(&pattern);
+        pattern[cluster] = false;
+        energy.update(cluster, -1.0);
+        let void = energy.largest_void(&pattern);
+        pattern[void] = true;
+        energy.update(void, 1.0);
+        if void == cluster {
+            break;
+        }
+    }
+
+    let mut ranks = vec![0usize; cells];
+
+    // Rank the initial points by removing the tightest cluster each time.
+    let mut removing = pattern.clone();
+    let mut removing_energy = Energy {
+        kernel: energy.kernel.clone(),
+        values: energy.values.clone(),
+    };
+    for rank in (0..ones).rev() {
+        let cluster = removing_energy.tightest_cluster(&removing);
+        removing[cluster] = false;
+        removing_energy.update(cluster, -1.0);
+        ranks[cluster] = rank;
+    }
+
+    // Rank the rest by filling the largest void each time.
+    for rank in ones..cells {
+        let void = energy.largest_void(&pattern);
+        pattern[void] = true;
+        energy.update(void, 1.0);
+        ranks[void] = rank;
+    }
+
+    ranks.into_iter().map(|rank| (rank as f64 + 0.5) / cells as f64).collect()
+}
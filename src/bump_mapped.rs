@@ -0,0 +1,64 @@
+use crate::color::Color;
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::texture::Texture;
+use crate::hittable::HitRecord;
+
+/// Step in texture space used to differentiate the height field.
+const DELTA: f64 = 1e-3;
+
+/// Perturbs the shading normal of a base material as if the surface were
+/// displaced along its normal by a height texture scaled by `scale`.
+pub struct BumpMapped {
+    base: Box<dyn Material>,
+    height: Box<dyn Texture>,
+    scale: f64,
+}
+
+impl BumpMapped {
+    pub fn new(base: Box<dyn Material>, height: Box<dyn Texture>, scale: f64) -> Self {
+        BumpMapped { base, height, scale }
+    }
+
+    fn height_at(&self, hit_record: &HitRecord, du: f64, dv: f64) -> f64 {
+        let point = hit_record
+            .point
+            .add(&hit_record.tangent.mul(du))
+            .add(&hit_record.bitangent.mul(dv));
+        self.height.value(hit_record.u + du, hit_record.v + dv, &point).r() * self.scale
+    }
+
+    fn shade<'a>(&self, ray_in: &Ray, hit_record: &HitRecord<'a>) -> HitRecord<'a> {
+        let height = self.height_at(hit_record, 0.0, 0.0);
+        let dhdu = (self.height_at(hit_record, DELTA, 0.0) - height) / DELTA;
+        let dhdv = (self.height_at(hit_record, 0.0, DELTA) - height) / DELTA;
+
+        let normal = hit_record.outward_normal();
+        let dpdu = hit_record.tangent.add(&normal.mul(dhdu));
+        let dpdv = hit_record.bitangent.add(&normal.mul(dhdv));
+        let mut perturbed = dpdu.cross(&dpdv).normalize();
+        if perturbed.dot(&normal) < 0.0 {
+            perturbed = perturbed.neg();
+        }
+        hit_record.with_shading_normal(perturbed, &ray_in.direction)
+    }
+}
+
+impl Material for BumpMapped {
+    fn scatter(&self, ray_in: &Ray, hit_record: &HitRecord) -> Option<(Color, Ray)> {
+        let shaded = self.shade(ray_in, hit_record);
+        let (attenuation, scattered) = self.base.scatter(ray_in, &shaded)?;
+        if !shaded.is_consistent(&scattered.direction) {
+            return None;
+        }
+        Some((attenuation, scattered))
+    }
+
+    fn emitted(&self, hit_record: &HitRecord) -> Color {
+        self.base.emitted(hit_record)
+    }
+
+    fn opacity(&self, hit_record: &HitRecord) -> f64 {
+        self.base.opacity(hit_record)
+    }
+}
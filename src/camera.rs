@@ -1,9 +1,25 @@
 use std::fs::File;
 use std::io::{BufWriter, Write};
-use rand::Rng;
+use std::path::Path;
+use std::time::Instant;
+use crate::adaptive::{write_heatmap, AdaptiveSampling};
+use crate::aov::{Aov, Aovs};
+use crate::checkpoint::{self, Progressive};
+use crate::aperture::Aperture;
 use crate::color::Color;
+use crate::denoiser::Denoiser;
+use crate::film::{Film, Guides, SampleRecord};
+use crate::filter::Filter;
 use crate::hittables::Hittables;
+use crate::lens_system::LensSystem;
+use crate::lpe::Lobe;
+use crate::physical_lens::PhysicalLens;
+use crate::projection::Projection;
 use crate::ray::Ray;
+use crate::sampler::{self, SamplerType};
+use crate::spectrum::{SampledSpectrum, SampledWavelengths};
+use crate::stereo::{Eye, Stereo};
+use crate::tone_mapping::DisplayTransform;
 use crate::utils::lerp;
 use crate::vector3::{Point3, Vector3};
 
@@ -33,9 +49,35 @@ pub struct Camera {
     pub focus_distance: f64,
     pub defocus_disk_u: Vector3,
     pub defocus_disk_v: Vector3,
+    pub aperture: Aperture,
+    pub projection: Projection,
+    pub stereo: Option<Stereo>,
+    /// Trace through real lens elements instead of the thin lens model.
+    pub lens_system: Option<LensSystem>,
+    /// Derive `vfov` and `defocus_angle` from focal length, sensor and f-stop.
+    pub physical_lens: Option<PhysicalLens>,
+    pub sampler: SamplerType,
+    /// Reconstruction filter splatting samples into the image.
+    pub filter: Filter,
+    /// Vary the samples per pixel with the noise instead of taking exactly
+    /// `samples_per_pixel` everywhere.
+    pub adaptive: Option<AdaptiveSampling>,
+    /// Render in passes, saving checkpoints to resume from.
+    pub progressive: Option<Progressive>,
+    /// Edge-avoiding filter run on the finished image.
+    pub denoiser: Option<Denoiser>,
+    /// Extra layers written to an OpenEXR file.
+    pub aovs: Option<Aovs>,
+    /// Exposure, tone mapping and encoding of the output image.
+    pub display: DisplayTransform,
+    /// Seed of the sampler; the same seed reproduces the same image.
+    pub seed: u64,
+    /// Trace sampled wavelengths instead of RGB triples.
+    pub spectral: bool,
 }
 
 impl Camera {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         aspect_ratio: f64,
         image_width: u32,
@@ -48,130 +90,667 @@ impl Camera {
         defocus_angle: f64,
         focus_distance: f64,
     ) -> Self {
-        let image_height = match (image_width as f64 / aspect_ratio) as u32 {
-            0 => 1,
-            height => height,
-        };
-        let viewport_aspect_ratio = image_width as f64 / image_height as f64;
-        let theta = vfov.to_radians();
-        let h = (theta / 2.0).tan();
-        let viewport_height = 2.0 * h * focus_distance;
-        let viewport_width = viewport_height * viewport_aspect_ratio;
-        let camera_w = lookfrom.sub(&lookat).normalize();
-        let camera_u = vup.cross(&camera_w).normalize();
-        let camera_v = camera_w.cross(&camera_u);
-        let viewport_u = camera_u.mul(viewport_width);
-        let viewport_v = camera_v.neg().mul(viewport_height);
-        let pixel_delta_u = viewport_u.div(image_width as f64);
-        let pixel_delta_v = viewport_v.div(image_height as f64);
-        let viewport_upper_left = lookfrom
-            .sub(&viewport_u.div(2.0))
-            .sub(&viewport_v.div(2.0))
-            .sub(&camera_w.mul(focus_distance));
-        let pixel_upper_left = viewport_upper_left.add(&pixel_delta_u.add(&pixel_delta_v).mul(0.5));
-        let defocus_radius = focus_distance * (defocus_angle.to_radians() / 2.0).tan();
-        let defocus_disk_u = camera_u.mul(defocus_radius);
-        let defocus_disk_v = camera_v.mul(defocus_radius);
-        Self {
+        let mut camera = Self {
             aspect_ratio,
             image_width,
-            image_height,
-            viewport_width,
-            viewport_height,
+            image_height: 0,
+            viewport_width: 0.0,
+            viewport_height: 0.0,
             vfov,
             lookfrom,
             lookat,
             vup,
-            camera_u,
-            camera_v,
-            camera_w,
+            camera_u: Vector3::default(),
+            camera_v: Vector3::default(),
+            camera_w: Vector3::default(),
             samples_per_pixel,
             max_depth,
-            viewport_u,
-            viewport_v,
-            pixel_delta_u,
-            pixel_delta_v,
-            viewport_upper_left,
-            pixel_upper_left,
+            viewport_u: Vector3::default(),
+            viewport_v: Vector3::default(),
+            pixel_delta_u: Vector3::default(),
+            pixel_delta_v: Vector3::default(),
+            viewport_upper_left: Point3::default(),
+            pixel_upper_left: Point3::default(),
             defocus_angle,
             focus_distance,
-            defocus_disk_u,
-            defocus_disk_v,
+            defocus_disk_u: Vector3::default(),
+            defocus_disk_v: Vector3::default(),
+            aperture: Aperture::circular(),
+            projection: Projection::Perspective,
+            stereo: None,
+            lens_system: None,
+            physical_lens: None,
+            sampler: SamplerType::Independent,
+            filter: Filter::Box { radius: 0.5 },
+            adaptive: None,
+            progressive: None,
+            denoiser: None,
+            aovs: None,
+            display: DisplayTransform::new(),
+            seed: 0,
+            spectral: false,
+        };
+        camera.initialize();
+        camera
+    }
+
+    /// Recompute the derived viewport and lens vectors from the settings.
+    fn initialize(&mut self) {
+        self.image_height = match (self.image_width as f64 / self.aspect_ratio) as u32 {
+            0 => 1,
+            height => height,
+        };
+        let viewport_aspect_ratio = self.image_width as f64 / self.image_height as f64;
+        if let Some(lens) = self.physical_lens {
+            self.vfov = lens.vfov(viewport_aspect_ratio);
+            self.defocus_angle = lens.defocus_angle(self.focus_distance);
+        }
+        // Panoramic projections map pixels to directions without a viewport.
+        self.viewport_height = match self.projection {
+            Projection::Orthographic { viewport_height } => viewport_height,
+            _ => {
+                let theta = self.vfov.to_radians();
+                let h = (theta / 2.0).tan();
+                2.0 * h * self.focus_distance
+            }
+        };
+        self.viewport_width = self.viewport_height * viewport_aspect_ratio;
+        self.camera_w = self.lookfrom.sub(&self.lookat).normalize();
+        self.camera_u = self.vup.cross(&self.camera_w).normalize();
+        self.camera_v = self.camera_w.cross(&self.camera_u);
+        self.viewport_u = self.camera_u.mul(self.viewport_width);
+        self.viewport_v = self.camera_v.neg().mul(self.viewport_height);
+        self.pixel_delta_u = self.viewport_u.div(self.image_width as f64);
+        self.pixel_delta_v = self.viewport_v.div(self.image_height as f64);
+        self.viewport_upper_left = self
+            .lookfrom
+            .sub(&self.viewport_u.div(2.0))
+            .sub(&self.viewport_v.div(2.0))
+            .sub(&self.camera_w.mul(self.focus_distance));
+        self.pixel_upper_left = self
+            .viewport_upper_left
+            .add(&self.pixel_delta_u.add(&self.pixel_delta_v).mul(0.5));
+        let defocus_radius = self.focus_distance * (self.defocus_angle.to_radians() / 2.0).tan();
+        self.defocus_disk_u = self.camera_u.mul(defocus_radius);
+        self.defocus_disk_v = self.camera_v.mul(defocus_radius);
+        if let Some(lens_system) = &mut self.lens_system {
+            lens_system.configure(viewport_aspect_ratio, self.focus_distance);
         }
     }
 
-    fn get_ray(&self, i: u32, j: u32) -> Ray {
-        let offset = self.sample_square();
-        let pixel_sample = self.pixel_upper_left
+    pub fn with_projection(mut self, projection: Projection) -> Self {
+        self.projection = projection;
+        self.initialize();
+        self
+    }
+
+    /// Shape the defocus disk, and so the bokeh, with `aperture`.
+    pub fn with_aperture(mut self, aperture: Aperture) -> Self {
+        self.aperture = aperture;
+        self
+    }
+
+    /// Render a left/right pair packed into one image.
+    pub fn with_stereo(mut self, stereo: Stereo) -> Self {
+        self.stereo = Some(stereo);
+        self
+    }
+
+    /// Replace the thin lens with a traced lens system focused at
+    /// `focus_distance`. `vfov` and `defocus_angle` are then ignored.
+    pub fn with_lens_system(mut self, lens_system: LensSystem) -> Self {
+        if self.focus_distance < lens_system.min_focus_distance() {
+            panic!("Focus distance is closer than the lens system can focus");
+        }
+        self.lens_system = Some(lens_system);
+        self.initialize();
+        self
+    }
+
+    pub fn with_physical_lens(mut self, lens: PhysicalLens) -> Self {
+        self.physical_lens = Some(lens);
+        self.initialize();
+        self
+    }
+
+    /// Focus on whatever surface is seen through the centre of pixel
+    /// `(i, j)`. Returns the new focus distance, or `None` if the pixel
+    /// sees no surface, or one nearer than the lens system can focus, and
+    /// the focus is left unchanged.
+    pub fn autofocus(&mut self, i: u32, j: u32, hittables: &Hittables) -> Option<f64> {
+        let ray = self.pinhole_ray(i, j)?;
+        let hit = hittables.hit_surfaces(&ray, 0.001, f64::INFINITY)?;
+        // The plane in focus is perpendicular to the view direction.
+        let distance = ray.direction.mul(hit.t).dot(&self.camera_w.neg());
+        let min_distance = self.lens_system.as_ref().map_or(0.0, |lens| lens.min_focus_distance());
+        if distance <= min_distance {
+            return None;
+        }
+        self.focus_distance = distance;
+        self.initialize();
+        Some(distance)
+    }
+
+    /// Aim at the centre of the scene bounds and back off along the current
+    /// view direction until their bounding sphere fits in the frame.
+    pub fn frame_all(&mut self, hittables: &Hittables) {
+        let Some(bounds) = hittables.bounding_box() else {
+            return;
+        };
+        let center = bounds.center();
+        let radius = 0.5 * bounds.size().length();
+        let aspect_ratio = self.image_width as f64 / self.image_height as f64;
+        let distance = match self.projection {
+            Projection::Orthographic { .. } => {
+                self.projection = Projection::Orthographic {
+                    viewport_height: 2.0 * radius * (1.0 / aspect_ratio).max(1.0),
+                };
+                2.0 * radius
+            }
+            _ => {
+                let half_vfov = self.vfov.to_radians() / 2.0;
+                let half_hfov = (half_vfov.tan() * aspect_ratio).atan();
+                radius / half_vfov.min(half_hfov).sin()
+            }
+        };
+        self.lookfrom = center.add(&self.camera_w.mul(distance));
+        self.lookat = center;
+        self.initialize();
+    }
+
+    /// Ray from the lens centre through the centre of pixel `(i, j)`.
+    fn pinhole_ray(&self, i: u32, j: u32) -> Option<Ray> {
+        match self.projection {
+            Projection::Perspective | Projection::Orthographic { .. } => {
+                let pixel_center = self.pixel_upper_left
+                    .add(&self.pixel_delta_u.mul(i as f64))
+                    .add(&self.pixel_delta_v.mul(j as f64));
+                let origin = match self.projection {
+                    Projection::Orthographic { .. } => pixel_center.add(&self.camera_w.mul(self.focus_distance)),
+                    _ => self.lookfrom,
+                };
+                Some(Ray::new(origin, pixel_center.sub(&origin).normalize()))
+            }
+            projection => {
+                let local = projection.direction(
+                    i as f64 + 0.5,
+                    j as f64 + 0.5,
+                    self.image_width as f64,
+                    self.image_height as f64,
+                )?;
+                Some(Ray::new(self.lookfrom, self.camera_to_world(&local).normalize()))
+            }
+        }
+    }
+
+    pub fn with_sampler(mut self, sampler: SamplerType) -> Self {
+        self.sampler = sampler;
+        self
+    }
+
+    pub fn with_filter(mut self, filter: Filter) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    pub fn with_adaptive_sampling(mut self, adaptive: AdaptiveSampling) -> Self {
+        self.adaptive = Some(adaptive);
+        self
+    }
+
+    pub fn with_progressive(mut self, progressive: Progressive) -> Self {
+        self.progressive = Some(progressive);
+        self
+    }
+
+    pub fn with_denoiser(mut self, denoiser: Denoiser) -> Self {
+        self.denoiser = Some(denoiser);
+        self
+    }
+
+    pub fn with_aovs(mut self, aovs: Aovs) -> Self {
+        self.aovs = Some(aovs);
+        self
+    }
+
+    pub fn with_display(mut self, display: DisplayTransform) -> Self {
+        self.display = display;
+        self
+    }
+
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    pub fn with_spectral(mut self, spectral: bool) -> Self {
+        self.spectral = spectral;
+        self
+    }
+
+    /// Ray through pixel `(i, j)` at `offset` from its centre as seen by
+    /// `eye` with its sample weight, or `None` if the projection maps
+    /// nothing there or the lens blocks it.
+    fn get_ray(&self, i: u32, j: u32, offset: &Vector3, eye: Option<Eye>) -> Option<(Ray, f64)> {
+        if let Some(lens_system) = &self.lens_system {
+            let (local, weight) = lens_system.generate_ray(
+                (i as f64 + 0.5 + offset.x) / self.image_width as f64,
+                (j as f64 + 0.5 + offset.y) / self.image_height as f64,
+            )?;
+            let mut origin = self.lookfrom.add(&self.camera_to_world(&local.origin));
+            if let (Some(stereo), Some(eye)) = (self.stereo, eye) {
+                origin = origin.add(&self.camera_u.mul(stereo.eye_offset(eye)));
+            }
+            return Some((Ray::new(origin, self.camera_to_world(&local.direction)), weight));
+        }
+        match self.projection {
+            Projection::Perspective | Projection::Orthographic { .. } => Some((self.get_planar_ray(i, j, offset, eye), 1.0)),
+            projection => {
+                let local = projection.direction(
+                    i as f64 + 0.5 + offset.x,
+                    j as f64 + 0.5 + offset.y,
+                    self.image_width as f64,
+                    self.image_height as f64,
+                )?;
+                let (origin, direction) = match (self.stereo, eye) {
+                    (Some(stereo), Some(eye)) => stereo.omnidirectional(eye, &local),
+                    _ => (Vector3::default(), local),
+                };
+                Some((Ray::new(self.lookfrom.add(&self.camera_to_world(&origin)), self.camera_to_world(&direction)), 1.0))
+            }
+        }
+    }
+
+    /// Convert a camera space vector (x right, y up, z forward) to world space.
+    fn camera_to_world(&self, local: &Vector3) -> Vector3 {
+        self.camera_u
+            .mul(local.x)
+            .add(&self.camera_v.mul(local.y))
+            .sub(&self.camera_w.mul(local.z))
+    }
+
+    fn get_planar_ray(&self, i: u32, j: u32, offset: &Vector3, eye: Option<Eye>) -> Ray {
+        let mut pixel_sample = self.pixel_upper_left
             .add(&self.pixel_delta_u.mul(i as f64 + offset.x))
             .add(&self.pixel_delta_v.mul(j as f64 + offset.y));
+        // Orthographic rays start on the camera plane behind their pixel and
+        // all run parallel to the view direction.
+        let mut lens_center = match self.projection {
+            Projection::Orthographic { .. } => pixel_sample.add(&self.camera_w.mul(self.focus_distance)),
+            _ => self.lookfrom,
+        };
+        // Shift the eye sideways and its viewport by less, so that both eyes
+        // see the same image on the convergence plane.
+        if let (Some(stereo), Some(eye)) = (self.stereo, eye) {
+            let eye_shift = self.camera_u.mul(stereo.eye_offset(eye));
+            let convergence = 1.0 - self.focus_distance / stereo.convergence_distance;
+            pixel_sample = pixel_sample.add(&eye_shift.mul(convergence));
+            lens_center = lens_center.add(&eye_shift);
+        }
         let ray_origin = if self.defocus_angle <= 0.0 {
-            self.lookfrom
+            lens_center
         } else {
-            self.defocus_disk_sample()
+            self.defocus_disk_sample(&lens_center)
         };
         let ray_direction = pixel_sample.sub(&ray_origin);
         Ray::new(ray_origin, ray_direction)
     }
 
     fn sample_square(&self) -> Vector3 {
-        let mut rng = rand::thread_rng();
-        let px = -0.5 + rng.gen::<f64>();
-        let py = -0.5 + rng.gen::<f64>();
-        Vector3::new(px, py, 0.0)
+        let (px, py) = sampler::get_2d();
+        Vector3::new(px - 0.5, py - 0.5, 0.0)
     }
 
-    fn defocus_disk_sample(&self) -> Vector3 {
-        let p = Vector3::random_in_unit_disk();
-        self.defocus_disk_u.mul(p.x).add(&self.defocus_disk_v.mul(p.y)).add(&self.lookfrom)
+    fn defocus_disk_sample(&self, center: &Point3) -> Vector3 {
+        let p = self.aperture.sample();
+        self.defocus_disk_u.mul(p.x).add(&self.defocus_disk_v.mul(p.y)).add(center)
     }
 
-    fn ray_color(&self, ray: &Ray, hittables: &Hittables, depth: u32) -> Color {
-        if depth <= 0 {
-            return Color::new(0.0, 0.0, 0.0);
+    /// Radiance arriving along `ray`, following the path one bounce at a
+    /// time while carrying its throughput. `record` receives the guides of
+    /// the first hit and every emission in the light group of its source
+    /// and the light path buckets matching the events on the way.
+    fn ray_color(&self, mut ray: Ray, hittables: &Hittables, record: &mut SampleRecord) -> Color {
+        let mut color = Color::new(0.0, 0.0, 0.0);
+        let mut throughput = Color::new(1.0, 1.0, 1.0);
+        let mut path = vec![b'C'];
+        for bounce in 0..self.max_depth {
+            sampler::start_bounce(bounce);
+            let Some(hit) = hittables.hit(&ray, 0.001, f64::INFINITY) else {
+                let background = self.background(&ray);
+                if bounce == 0 {
+                    record.guides = Guides::miss(background);
+                }
+                let contribution = throughput.mul_color(&background);
+                self.record_light(record, hittables.light_group_names.len(), &mut path, b'B', &contribution);
+                return color.add(&contribution);
+            };
+            let emitted = hit.material.emitted(&hit);
+            let scatter = hit.material.scatter(&ray, &hit);
+            if bounce == 0 {
+                record.guides = Guides::hit(&ray, &hit, scatter.as_ref().map_or(emitted, |(attenuation, _)| *attenuation));
+            }
+            let contribution = throughput.mul_color(&emitted);
+            self.record_light(record, hittables.light_group(hit.object_id), &mut path, b'L', &contribution);
+            color = color.add(&contribution);
+            let Some((attenuation, scattered)) = scatter else {
+                return color;
+            };
+            throughput = throughput.mul_color(&attenuation);
+            path.push(scattered.lobe.unwrap_or(Lobe::Diffuse).event());
+            ray = scattered;
         }
+        color
+    }
 
-        if let Some(hit) = hittables.hit(ray, 0.001, f64::INFINITY) {
-            if let Some((attenuation, scattered)) = hit.material.scatter(ray, &hit) {
-                let color = self.ray_color(&scattered, hittables, depth - 1);
-                return Color::new(
-                    attenuation.r() * color.r(),
-                    attenuation.g() * color.g(),
-                    attenuation.b() * color.b(),
-                );
+    /// Spectral counterpart of `ray_color`. RGB attenuations and the
+    /// background are upsampled to the wavelengths carried by the ray.
+    /// Light groups are converted to RGB per contribution, so with out of
+    /// gamut colors they add up to the beauty only approximately.
+    fn ray_spectrum(&self, mut ray: Ray, hittables: &Hittables, record: &mut SampleRecord) -> SampledSpectrum {
+        let camera_wavelengths = ray.wavelengths.expect("Spectral rays must carry wavelengths");
+        let mut spectrum = SampledSpectrum::new(0.0);
+        let mut throughput = SampledSpectrum::new(1.0);
+        let mut path = vec![b'C'];
+        for bounce in 0..self.max_depth {
+            sampler::start_bounce(bounce);
+            let wavelengths = ray.wavelengths.expect("Spectral rays must carry wavelengths");
+            let Some(hit) = hittables.hit(&ray, 0.001, f64::INFINITY) else {
+                let background = self.background(&ray);
+                if bounce == 0 {
+                    record.guides = Guides::miss(background);
+                }
+                let contribution = throughput.mul(&SampledSpectrum::from_rgb(&background, &wavelengths));
+                if record.records_light() {
+                    let color = contribution.to_color(&camera_wavelengths);
+                    self.record_light(record, hittables.light_group_names.len(), &mut path, b'B', &color);
+                }
+                return spectrum.add(&contribution);
+            };
+            let emitted_color = hit.material.emitted(&hit);
+            let scatter = hit.material.scatter(&ray, &hit);
+            if bounce == 0 {
+                record.guides = Guides::hit(&ray, &hit, scatter.as_ref().map_or(emitted_color, |(attenuation, _)| *attenuation));
             }
-            return Color::new(0.0, 0.0, 0.0);
+            let contribution = throughput.mul(&SampledSpectrum::from_rgb(&emitted_color, &wavelengths));
+            if record.records_light() {
+                let color = contribution.to_color(&camera_wavelengths);
+                self.record_light(record, hittables.light_group(hit.object_id), &mut path, b'L', &color);
+            }
+            spectrum = spectrum.add(&contribution);
+            let Some((attenuation, mut scattered)) = scatter else {
+                return spectrum;
+            };
+            if scattered.wavelengths.is_none() {
+                scattered.wavelengths = ray.wavelengths;
+            }
+            throughput = throughput.mul(&SampledSpectrum::from_rgb(&attenuation, &wavelengths));
+            // Terminating the secondary wavelengths scales everything the
+            // path gathers from here on.
+            let terminated = scattered.wavelengths.is_some_and(|w| w.secondary_terminated);
+            if terminated && !wavelengths.secondary_terminated {
+                throughput = throughput.terminate_secondary();
+            }
+            path.push(scattered.lobe.unwrap_or(Lobe::Diffuse).event());
+            ray = scattered;
         }
+        spectrum
+    }
 
+    /// Add light reaching the camera to light group `group` and to the
+    /// light path buckets matching `path` ended by the event `end`. Rays
+    /// from materials that do not tag their lobe count as diffuse.
+    fn record_light(&self, record: &mut SampleRecord, group: usize, path: &mut Vec<u8>, end: u8, contribution: &Color) {
+        if contribution.r() == 0.0 && contribution.g() == 0.0 && contribution.b() == 0.0 {
+            return;
+        }
+        record.add_light(group, contribution);
+        let Some(aovs) = &self.aovs else {
+            return;
+        };
+        path.push(end);
+        for (light, expression) in record.light_paths.iter_mut().zip(&aovs.light_paths) {
+            if expression.matches(path) {
+                *light = light.add(contribution);
+            }
+        }
+        path.pop();
+    }
+
+    fn background(&self, ray: &Ray) -> Color {
         let unit_direction = ray.direction.normalize();
         let t = 0.5 * (unit_direction.y + 1.0);
         lerp(Color::new(1.0, 1.0, 1.0), Color::new(0.5, 0.7, 1.0), t)
     }
 
+    /// Color of one sample, recording its first hit and light groups in
+    /// `record`.
+    fn sample_color(&self, i: u32, j: u32, offset: &Vector3, eye: Option<Eye>, hittables: &Hittables, record: &mut SampleRecord) -> Color {
+        let Some((ray, weight)) = self.get_ray(i, j, offset, eye) else {
+            return Color::new(0.0, 0.0, 0.0);
+        };
+        let color = if self.spectral {
+            let wavelengths = SampledWavelengths::sample_uniform(sampler::get_1d());
+            let ray = ray.with_wavelengths(Some(wavelengths));
+            self.ray_spectrum(ray, hittables, record).to_color(&wavelengths)
+        } else {
+            self.ray_color(ray, hittables, record)
+        };
+        record.scale(weight);
+        color.mul(weight)
+    }
+
+    /// Take the next sample of output pixel `(i, j)`, which for stereo
+    /// renders belongs to one of the packed eye images, record it in the
+    /// pixel's statistics and splat it into `film` within that image.
+    fn sample_pixel(&self, i: u32, j: u32, samples_per_pixel: u32, seed: u64, hittables: &Hittables, film: &mut Film) {
+        let (eye, x, y) = match self.stereo {
+            Some(stereo) => {
+                let (eye, x, y) = stereo.locate(i, j, self.image_width, self.image_height);
+                (Some(eye), x, y)
+            }
+            None => (None, i, j),
+        };
+        let k = (j * film.width + i) as usize;
+        sampler::start_pixel_sample(self.sampler, samples_per_pixel, seed, i, j, film.stats[k].count);
+        let offset = self.sample_square();
+        let light_paths = self.aovs.as_ref().map_or(0, |aovs| aovs.light_paths.len());
+        let mut record = SampleRecord::new(film.layer_names.len() - light_paths, light_paths);
+        let color = self.sample_color(x, y, &offset, eye, hittables, &mut record);
+        film.add_guides(k, &record.guides);
+        film.stats[k].add(&color);
+        let (x0, y0) = (i - x, j - y);
+        film.add_sample(
+            i as f64 + 0.5 + offset.x,
+            j as f64 + 0.5 + offset.y,
+            &color,
+            &record.layers(),
+            (x0, y0, x0 + self.image_width, y0 + self.image_height),
+        );
+    }
+
+    fn output_size(&self) -> (u32, u32) {
+        match self.stereo {
+            Some(stereo) => stereo.output_size(self.image_width, self.image_height),
+            None => (self.image_width, self.image_height),
+        }
+    }
+
     pub fn render(
         &self,
         writer: &mut BufWriter<File>,
         hittables: &Hittables,
     ) -> Result<(), std::io::Error> {
+        let (output_width, output_height) = self.output_size();
+        let (mut film, seed) = self.start_film(hittables)?;
+        match &self.adaptive {
+            Some(adaptive) => self.render_adaptive(adaptive, hittables, &mut film, seed)?,
+            None => self.render_passes(hittables, &mut film, seed)?,
+        }
+
         write!(
             writer,
             "P3\n{} {}\n255\n",
-            self.image_width, self.image_height
+            output_width, output_height
         )?;
+        let mut pixels = film.resolve();
+        if let Some(aovs) = &self.aovs {
+            aovs.write(&film, &pixels)?;
+        }
+        if let Some(denoiser) = &self.denoiser {
+            let regions = match self.stereo {
+                Some(stereo) => stereo.regions(self.image_width, self.image_height).to_vec(),
+                None => vec![(0, 0, output_width, output_height)],
+            };
+            pixels = denoiser.denoise(&film, &pixels, &regions);
+        }
+        for (k, pixel_color) in pixels.iter().enumerate() {
+            let [r, g, b] = self.display.encode(pixel_color, k as u32 % output_width, k as u32 / output_width);
+            writeln!(writer, "{} {} {}", r, g, b)?;
+        }
+        Ok(())
+    }
+
+    /// Empty film, or the one saved in the checkpoint when resuming. The
+    /// film has a layer per light group when those are output, and one per
+    /// light path expression.
+    fn start_film(&self, hittables: &Hittables) -> Result<(Film, u64), std::io::Error> {
+        let (output_width, output_height) = self.output_size();
+        let mut layers = Vec::new();
+        if self.aovs.as_ref().is_some_and(|aovs| aovs.has(Aov::LightGroups)) {
+            layers.extend(hittables.light_group_names.iter().map(|name| format!("light_{}", name)));
+            layers.push("light_background".to_string());
+        }
+        if let Some(aovs) = &self.aovs {
+            layers.extend(aovs.light_paths.iter().map(|expression| format!("lpe_{}", expression.name)));
+        }
+        if let Some(Progressive { checkpoint: Some(path), resume: true, .. }) = &self.progressive {
+            if Path::new(path).exists() {
+                let (film, seed) = checkpoint::load(path, self.filter, &layers)?;
+                if (film.width, film.height) != (output_width, output_height) {
+                    return Err(std::io::Error::new(
+                        std::io::ErrorKind::InvalidData,
+                        format!("{}: checkpoint is {}x{}, image is {}x{}", path, film.width, film.height, output_width, output_height),
+                    ));
+                }
+                println!("Resuming from {} with {} samples", path, film.sample_count());
+                return Ok((film, seed));
+            }
+        }
+        Ok((Film::new(output_width, output_height, self.filter).with_layers(&layers), self.seed))
+    }
 
-        for j in 0..self.image_height {
-            print!("\rScanlines remaining: {:>4}", self.image_height - j);
-            for i in 0..self.image_width {
-                let mut pixel_color = Color::new(0.0, 0.0, 0.0);
-                for _ in 0..self.samples_per_pixel {
-                    let ray = self.get_ray(i, j);
-                    pixel_color = pixel_color.add(&self.ray_color(&ray, &hittables, self.max_depth));
+    /// Save a checkpoint if one is due, or unconditionally once `finished`.
+    fn checkpoint(&self, film: &Film, seed: u64, last_saved: &mut Instant, finished: bool) -> Result<(), std::io::Error> {
+        if let Some(Progressive { checkpoint: Some(path), interval, .. }) = &self.progressive {
+            if finished || last_saved.elapsed() >= *interval {
+                checkpoint::save(path, film, seed)?;
+                *last_saved = Instant::now();
+            }
+        }
+        Ok(())
+    }
+
+    /// Bring every pixel up to `samples_per_pixel` samples, in passes of
+    /// `pass_samples` when rendering progressively. With a time limit or
+    /// error threshold, passes continue until that is reached instead. Only
+    /// whole passes are rendered, so that the noise is even across the
+    /// image, and none is started that would overrun the time limit.
+    fn render_passes(&self, hittables: &Hittables, film: &mut Film, seed: u64) -> Result<(), std::io::Error> {
+        let pass_samples = self.progressive.as_ref().map_or(self.samples_per_pixel, |progressive| progressive.pass_samples);
+        let open_ended = self.progressive.as_ref().is_some_and(|progressive| progressive.is_open_ended());
+        let passes = self.samples_per_pixel.div_ceil(pass_samples);
+        let started = Instant::now();
+        let mut last_saved = Instant::now();
+        for pass in 1.. {
+            if !open_ended && pass > passes {
+                break;
+            }
+            let target = if open_ended {
+                pass * pass_samples
+            } else {
+                (pass * pass_samples).min(self.samples_per_pixel)
+            };
+            if film.stats.iter().all(|pixel| pixel.count >= target) {
+                continue;
+            }
+            let pass_started = Instant::now();
+            for j in 0..film.height {
+                if open_ended {
+                    print!("\rPass {:>4}, scanlines remaining: {:>4}", pass, film.height - j);
+                } else {
+                    print!("\rPass {:>4}/{}, scanlines remaining: {:>4}", pass, passes, film.height - j);
+                }
+                for i in 0..film.width {
+                    let k = (j * film.width + i) as usize;
+                    while film.stats[k].count < target {
+                        self.sample_pixel(i, j, self.samples_per_pixel, seed, hittables, film);
+                    }
                 }
-                pixel_color = pixel_color.div(self.samples_per_pixel as f64);
-                pixel_color.dump(writer)?;
             }
+            let mut done = false;
+            if let Some(progressive) = self.progressive.as_ref().filter(|progressive| progressive.is_open_ended()) {
+                print!(", error: {:.4}", film.relative_error());
+                // Assume the next pass takes as long as this one.
+                let next_pass_end = started.elapsed() + pass_started.elapsed();
+                done = progressive.is_done(film, started) || progressive.time_limit.is_some_and(|limit| next_pass_end > limit);
+            }
+            self.checkpoint(film, seed, &mut last_saved, done || (!open_ended && pass == passes))?;
+            if done {
+                break;
+            }
+        }
+        println!("\r{:-^40}", "Done");
+        Ok(())
+    }
+
+    /// Spend `samples_per_pixel` samples per pixel on average, in passes
+    /// that only revisit pixels whose estimate has not converged. When the
+    /// budget runs short the noisiest pixels go first. With a time limit or
+    /// error threshold there is no budget, and those end the render.
+    fn render_adaptive(&self, adaptive: &AdaptiveSampling, hittables: &Hittables, film: &mut Film, seed: u64) -> Result<(), std::io::Error> {
+        let pixel_count = film.stats.len();
+        let open_ended = self.progressive.as_ref().is_some_and(|progressive| progressive.is_open_ended());
+        let mut budget = if open_ended {
+            u64::MAX
+        } else {
+            (self.samples_per_pixel as u64 * pixel_count as u64).saturating_sub(film.sample_count())
+        };
+        let batch = adaptive.min_samples;
+        let started = Instant::now();
+        let mut last_saved = Instant::now();
+
+        for pass in 1.. {
+            let mut active: Vec<usize> = (0..pixel_count).filter(|&k| !adaptive.is_converged(&film.stats[k])).collect();
+            if active.is_empty() || budget == 0 {
+                break;
+            }
+            if (active.len() as u64) * batch as u64 > budget {
+                active.sort_by(|&a, &b| adaptive.error(&film.stats[b]).total_cmp(&adaptive.error(&film.stats[a])));
+                active.truncate((budget / batch as u64).max(1) as usize);
+            }
+            if self.progressive.as_ref().is_some_and(|progressive| progressive.is_done(film, started)) {
+                break;
+            }
+            print!("\rPass {:>4}, active pixels: {:>8}", pass, active.len());
+            std::io::stdout().flush()?;
+            for &k in &active {
+                let (i, j) = (k as u32 % film.width, k as u32 / film.width);
+                let samples = batch.min(adaptive.max_samples - film.stats[k].count);
+                for _ in 0..samples {
+                    self.sample_pixel(i, j, adaptive.max_samples, seed, hittables, film);
+                }
+                budget = budget.saturating_sub(samples as u64);
+            }
+            self.checkpoint(film, seed, &mut last_saved, false)?;
+        }
+        self.checkpoint(film, seed, &mut last_saved, true)?;
+        println!("\r{:-^40}", "Done");
+
+        if let Some(path) = &adaptive.heatmap {
+            let counts: Vec<u32> = film.stats.iter().map(|pixel| pixel.count).collect();
+            write_heatmap(path, film.width, film.height, &counts)?;
         }
-        println!("\r{:-^30}", "Done");
         Ok(())
     }
 }
@@ -0,0 +1,197 @@
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, Error, ErrorKind, Read, Write};
+use std::time::{Duration, Instant};
+use crate::adaptive::PixelStats;
+use crate::color::Color;
+use crate::film::Film;
+use crate::filter::Filter;
+use crate::vector3::Vector3;
+
+const MAGIC: &[u8; 4] = b"BDCK";
+const VERSION: u32 = 3;
+
+/// Settings for rendering in passes and saving progress between them.
+#[derive(Debug, Clone)]
+pub struct Progressive {
+    /// Samples added to each pixel per pass.
+    pub pass_samples: u32,
+    pub checkpoint: Option<String>,
+    /// Minimum time between checkpoints. The last pass is always saved.
+    pub interval: Duration,
+    /// Continue from the checkpoint file if it exists.
+    pub resume: bool,
+    /// Stop once this much time has been spent rendering.
+    pub time_limit: Option<Duration>,
+    /// Stop once the image's mean relative error drops below this.
+    pub error_threshold: Option<f64>,
+}
+
+impl Progressive {
+    pub fn new(pass_samples: u32) -> Self {
+        if pass_samples == 0 {
+            panic!("Passes must take at least one sample");
+        }
+        Progressive {
+            pass_samples,
+            checkpoint: None,
+            interval: Duration::from_secs(60),
+            resume: false,
+            time_limit: None,
+            error_threshold: None,
+        }
+    }
+
+    /// Keep rendering passes until `time_limit` has passed rather than up
+    /// to the camera's `samples_per_pixel`.
+    pub fn with_time_limit(mut self, time_limit: Duration) -> Self {
+        self.time_limit = Some(time_limit);
+        self
+    }
+
+    /// Keep rendering passes until the image's mean relative error drops
+    /// below `threshold` rather than up to the camera's `samples_per_pixel`.
+    pub fn with_error_threshold(mut self, threshold: f64) -> Self {
+        if threshold <= 0.0 {
+            panic!("Error threshold must be positive");
+        }
+        self.error_threshold = Some(threshold);
+        self
+    }
+
+    /// Whether passes continue past `samples_per_pixel` until a limit is hit.
+    pub fn is_open_ended(&self) -> bool {
+        self.time_limit.is_some() || self.error_threshold.is_some()
+    }
+
+    /// Whether rendering should stop, having started at `started`.
+    pub fn is_done(&self, film: &Film, started: Instant) -> bool {
+        self.time_limit.is_some_and(|limit| started.elapsed() >= limit)
+            || self.error_threshold.is_some_and(|threshold| film.relative_error() <= threshold)
+    }
+
+    pub fn with_checkpoint(mut self, path: &str, interval: Duration) -> Self {
+        self.checkpoint = Some(path.to_string());
+        self.interval = interval;
+        self
+    }
+
+    pub fn with_resume(mut self, resume: bool) -> Self {
+        self.resume = resume;
+        self
+    }
+}
+
+/// Write the film and the sampler seed to `path`. The data goes to a
+/// temporary file first, so a crash mid-write leaves the previous
+/// checkpoint intact.
+pub fn save(path: &str, film: &Film, seed: u64) -> Result<(), Error> {
+    let temporary = format!("{}.tmp", path);
+    {
+        let mut writer = BufWriter::new(File::create(&temporary)?);
+        writer.write_all(MAGIC)?;
+        writer.write_all(&VERSION.to_le_bytes())?;
+        writer.write_all(&film.width.to_le_bytes())?;
+        writer.write_all(&film.height.to_le_bytes())?;
+        writer.write_all(&seed.to_le_bytes())?;
+        writer.write_all(&(film.layer_sums.len() as u32).to_le_bytes())?;
+        for k in 0..film.sums.len() {
+            let (sum, stats) = (&film.sums[k], &film.stats[k]);
+            for value in [sum.r(), sum.g(), sum.b(), film.weights[k]] {
+                writer.write_all(&value.to_le_bytes())?;
+            }
+            writer.write_all(&stats.count.to_le_bytes())?;
+            for value in [stats.mean.r(), stats.mean.g(), stats.mean.b(), stats.mean_luminance, stats.m2] {
+                writer.write_all(&value.to_le_bytes())?;
+            }
+            let (albedo, normal) = (&film.albedo_sums[k], &film.normal_sums[k]);
+            for value in [albedo.r(), albedo.g(), albedo.b(), normal.x, normal.y, normal.z, film.depth_sums[k]] {
+                writer.write_all(&value.to_le_bytes())?;
+            }
+            let position = &film.position_sums[k];
+            for value in [position.x, position.y, position.z] {
+                writer.write_all(&value.to_le_bytes())?;
+            }
+            for value in [film.hit_counts[k], film.object_ids[k], film.material_ids[k]] {
+                writer.write_all(&value.to_le_bytes())?;
+            }
+            for sums in &film.layer_sums {
+                for value in [sums[k].r(), sums[k].g(), sums[k].b()] {
+                    writer.write_all(&value.to_le_bytes())?;
+                }
+            }
+        }
+        writer.flush()?;
+    }
+    fs::rename(&temporary, path)
+}
+
+/// Read a checkpoint written by `save`, returning the film and its seed.
+/// The filter and layer names are not stored and must match the ones it
+/// was rendered with.
+pub fn load(path: &str, filter: Filter, layer_names: &[String]) -> Result<(Film, u64), Error> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let invalid = |message: &str| Error::new(ErrorKind::InvalidData, format!("{}: {}", path, message));
+
+    let mut magic = [0u8; 4];
+    reader.read_exact(&mut magic)?;
+    if &magic != MAGIC {
+        return Err(invalid("not a checkpoint file"));
+    }
+    if read_u32(&mut reader)? != VERSION {
+        return Err(invalid("unsupported checkpoint version"));
+    }
+    let width = read_u32(&mut reader)?;
+    let height = read_u32(&mut reader)?;
+    let seed = read_u64(&mut reader)?;
+    if read_u32(&mut reader)? as usize != layer_names.len() {
+        return Err(invalid("checkpoint has different layers"));
+    }
+
+    let mut film = Film::new(width, height, filter).with_layers(layer_names);
+    for k in 0..(width * height) as usize {
+        let (r, g, b, weight) = (read_f64(&mut reader)?, read_f64(&mut reader)?, read_f64(&mut reader)?, read_f64(&mut reader)?);
+        film.sums[k] = Color::from_weighted_sum(Vector3::new(r, g, b));
+        film.weights[k] = weight;
+        let count = read_u32(&mut reader)?;
+        let (r, g, b) = (read_f64(&mut reader)?, read_f64(&mut reader)?, read_f64(&mut reader)?);
+        film.stats[k] = PixelStats {
+            count,
+            mean: Color::new(r, g, b),
+            mean_luminance: read_f64(&mut reader)?,
+            m2: read_f64(&mut reader)?,
+        };
+        let (r, g, b) = (read_f64(&mut reader)?, read_f64(&mut reader)?, read_f64(&mut reader)?);
+        film.albedo_sums[k] = Color::new(r, g, b);
+        let (x, y, z) = (read_f64(&mut reader)?, read_f64(&mut reader)?, read_f64(&mut reader)?);
+        film.normal_sums[k] = Vector3::new(x, y, z);
+        film.depth_sums[k] = read_f64(&mut reader)?;
+        let (x, y, z) = (read_f64(&mut reader)?, read_f64(&mut reader)?, read_f64(&mut reader)?);
+        film.position_sums[k] = Vector3::new(x, y, z);
+        film.hit_counts[k] = read_u32(&mut reader)?;
+        film.object_ids[k] = read_u32(&mut reader)?;
+        film.material_ids[k] = read_u32(&mut reader)?;
+        for sums in film.layer_sums.iter_mut() {
+            let (r, g, b) = (read_f64(&mut reader)?, read_f64(&mut reader)?, read_f64(&mut reader)?);
+            sums[k] = Color::from_weighted_sum(Vector3::new(r, g, b));
+        }
+    }
+    Ok((film, seed))
+}
+
+fn read_u32<R: Read>(reader: &mut R) -> Result<u32, Error> {
+    let mut bytes = [0u8; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn read_u64<R: Read>(reader: &mut R) -> Result<u64, Error> {
+    let mut bytes = [0u8; 8];
+    reader.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+fn read_f64<R: Read>(reader: &mut R) -> Result<f64, Error> {
+    let mut bytes = [0u8; 8];
+    reader.read_exact(&mut bytes)?;
+    Ok(f64::from_le_bytes(bytes))
+}
@@ -0,0 +1,92 @@
+use crate::color::Color;
+use crate::material::Material;
+use crate::microfacet::{fresnel_dielectric, Ggx};
+use crate::onb::Onb;
+use crate::lpe::Lobe;
+use crate::ray::Ray;
+use crate::thin_film::{Substrate, ThinFilm};
+use crate::hittable::HitRecord;
+use crate::sampler;
+
+/// A dielectric clearcoat layered over any base material. Light reflects
+/// off the coat with its Fresnel reflectance and otherwise passes through to
+/// the base, treating the coat as infinitely thin.
+pub struct Coated {
+    pub base: Box<dyn Material>,
+    pub refraction_index: f64,
+    pub roughness: f64,
+    pub thin_film: Option<ThinFilm>,
+}
+
+impl Coated {
+    pub fn new(base: Box<dyn Material>, refraction_index: f64, roughness: f64) -> Self {
+        if !(0.0..=1.0).contains(&roughness) {
+            panic!("Roughness must be between 0.0 and 1.0");
+        }
+        Coated {
+            base,
+            refraction_index,
+            roughness,
+            thin_film: None,
+        }
+    }
+
+    /// Add an interference film on top of the coat, e.g. a lens coating.
+    pub fn with_thin_film(mut self, thin_film: ThinFilm) -> Self {
+        self.thin_film = Some(thin_film);
+        self
+    }
+}
+
+impl Material for Coated {
+    fn scatter(&self, ray_in: &Ray, hit_record: &HitRecord) -> Option<(Color, Ray)> {
+        if !hit_record.front_face {
+            return self.base.scatter(ray_in, hit_record);
+        }
+
+        let cos_theta = ray_in.direction.normalize().neg().dot(&hit_record.normal);
+        let (reflectance, wavelengths) = match &self.thin_film {
+            Some(thin_film) => thin_film.reflectance_for(ray_in, cos_theta, 1.0, |_| {
+                Substrate::Dielectric(self.refraction_index)
+            }),
+            None => {
+                let r = fresnel_dielectric(cos_theta, 1.0 / self.refraction_index);
+                (Color::new(r, r, r), ray_in.wavelengths)
+            }
+        };
+        let probability = (reflectance.r() + reflectance.g() + reflectance.b()) / 3.0;
+
+        if sampler::get_1d() < probability {
+            let onb = Onb::new(&hit_record.normal);
+            let wo = onb.to_local(&ray_in.direction.normalize().neg());
+            let ggx = Ggx::from_roughness(self.roughness);
+            let (u1, u2) = sampler::get_2d();
+            let (wi, _) = ggx.sample_reflection(&wo, u1, u2)?;
+            let attenuation = reflectance.div(probability).mul(ggx.sample_weight(&wi));
+            let scattered = Ray::new(hit_record.point, onb.to_world(&wi))
+                .with_wavelengths(wavelengths)
+                .with_lobe(Lobe::Specular);
+            return Some((attenuation, scattered));
+        }
+
+        let (attenuation, scattered) = self.base.scatter(ray_in, hit_record)?;
+        let transmittance = Color::new(1.0, 1.0, 1.0).sub(&reflectance).div(1.0 - probability);
+        // Keep the base's wavelengths, but a hero-only coat evaluation
+        // must terminate the secondaries for the whole path.
+        let terminated = wavelengths.is_some_and(|w| w.secondary_terminated);
+        let base_wavelengths = scattered
+            .wavelengths
+            .or(wavelengths)
+            .map(|w| if terminated { w.terminate_secondary() } else { w });
+        let scattered = scattered.with_wavelengths(base_wavelengths);
+        Some((transmittance.mul_color(&attenuation), scattered))
+    }
+
+    fn emitted(&self, hit_record: &HitRecord) -> Color {
+        self.base.emitted(hit_record)
+    }
+
+    fn opacity(&self, hit_record: &HitRecord) -> f64 {
+        self.base.opacity(hit_record)
+    }
+}
@@ -1,24 +1,20 @@
+use crate::tone_mapping::DisplayTransform;
 use crate::vector3::Vector3;
 use std::fmt::{self, Display};
-use std::io::Write;
-
-fn to_256(x: f64) -> u8 {
-    (x * 255.0) as u8
-}
 
 fn check_color_value(x: f64) {
-    if x < 0.0 || x > 1.0 {
+    if !(x >= 0.0 && x.is_finite()) {
         panic!("Invalid color value: {}", x);
     }
 }
 
-fn linear_to_gamma(x: f64) -> f64 {
-    if x < 0.0 {
-        0.0
-    } else if x > 1.0 {
-        1.0
+/// sRGB transfer function, clipping `x` to [0, 1] first.
+pub fn linear_to_srgb(x: f64) -> f64 {
+    let x = x.clamp(0.0, 1.0);
+    if x <= 0.0031308 {
+        12.92 * x
     } else {
-        x.sqrt()
+        1.055 * x.powf(1.0 / 2.4) - 0.055
     }
 }
 
@@ -29,7 +25,8 @@ pub struct Color {
 
 impl Color {
     /// Create a new color with the given red, green, and blue values.
-    /// All values must be between 0.0 and 1.0.
+    /// All values must be finite and non-negative. Reflectances stay within
+    /// 0.0 and 1.0; radiance may go above 1.0 and is clipped on output.
     pub fn new(r: f64, g: f64, b: f64) -> Self {
         check_color_value(r);
         check_color_value(g);
@@ -79,27 +76,38 @@ impl Color {
         }
     }
 
+    /// Component-wise product, used to attenuate light by a surface color.
+    pub fn mul_color(&self, other: &Color) -> Self {
+        Self {
+            vec: Vector3::new(
+                self.vec.x * other.vec.x,
+                self.vec.y * other.vec.y,
+                self.vec.z * other.vec.z,
+            ),
+        }
+    }
+
+    /// Relative luminance of a linear sRGB color.
+    pub fn luminance(&self) -> f64 {
+        0.2126 * self.r() + 0.7152 * self.g() + 0.0722 * self.b()
+    }
+
     /// Convert from Vector3
     pub fn from_vec(v: Vector3) -> Self {
         Self::new(v.x, v.y, v.z)
     }
 
+    /// Convert from a Vector3 of filter-weighted sums, which negative
+    /// filter lobes can take below zero. Unlike `from_vec`, the values are
+    /// not checked.
+    pub fn from_weighted_sum(v: Vector3) -> Self {
+        Self { vec: v }
+    }
+
     /// Convert to Vector3
     pub fn as_vec(&self) -> &Vector3 {
         &self.vec
     }
-
-    /// Dump the color to a writer.
-    /// Scales the color to the range 0-255 and writes it to the writer.
-    pub fn dump<T: Write>(&self, out: &mut T) -> Result<(), std::io::Error> {
-        write!(
-            out,
-            "{} {} {}\n",
-            to_256(linear_to_gamma(self.r())),
-            to_256(linear_to_gamma(self.g())),
-            to_256(linear_to_gamma(self.b()))
-        )
-    }
 }
 
 // Implement From for clean conversions
@@ -112,13 +120,8 @@ impl From<Vector3> for Color {
 impl Display for Color {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "Color({} {} {})", self.r(), self.g(), self.b())?;
-        write!(
-            f,
-            " RGB({} {} {})",
-            to_256(self.r()),
-            to_256(self.g()),
-            to_256(self.b())
-        )?;
+        let [r, g, b] = DisplayTransform::new().encode(self, 0, 0);
+        write!(f, " RGB({} {} {})", r, g, b)?;
         Ok(())
     }
 }
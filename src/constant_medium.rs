@@ -0,0 +1,63 @@
+use crate::aabb::Aabb;
+use crate::hittable::{HitRecord, Hittable};
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::vector3::Vector3;
+use crate::sampler;
+
+/// Homogeneous participating medium filling a closed boundary shape,
+/// such as smoke or mist inside a `Sphere`.
+pub struct ConstantMedium {
+    boundary: Box<dyn Hittable>,
+    neg_inv_density: f64,
+    phase_function: Box<dyn Material>,
+}
+
+impl ConstantMedium {
+    pub fn new(boundary: Box<dyn Hittable>, density: f64, phase_function: Box<dyn Material>) -> Self {
+        if density <= 0.0 {
+            panic!("Density must be positive");
+        }
+        ConstantMedium {
+            boundary,
+            neg_inv_density: -1.0 / density,
+            phase_function,
+        }
+    }
+}
+
+impl Hittable for ConstantMedium {
+    fn hit(&self, ray: &Ray, ray_tmin: f64, ray_tmax: f64) -> Option<HitRecord<'_>> {
+        let mut t_enter = self.boundary.hit(ray, f64::NEG_INFINITY, f64::INFINITY)?.t;
+        let mut t_exit = self.boundary.hit(ray, t_enter + 0.0001, f64::INFINITY)?.t;
+
+        t_enter = t_enter.max(ray_tmin).max(0.0);
+        t_exit = t_exit.min(ray_tmax);
+        if t_enter >= t_exit {
+            return None;
+        }
+
+        let ray_length = ray.direction.length();
+        let distance_inside_boundary = (t_exit - t_enter) * ray_length;
+        let hit_distance = self.neg_inv_density * sampler::get_1d().ln();
+        if hit_distance > distance_inside_boundary {
+            return None;
+        }
+
+        let t = t_enter + hit_distance / ray_length;
+        // The normal is arbitrary inside a medium.
+        Some(HitRecord::new(
+            ray.at(t),
+            Vector3::new(1.0, 0.0, 0.0),
+            t,
+            0.0,
+            0.0,
+            ray.direction,
+            &*self.phase_function,
+        ))
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.boundary.bounding_box()
+    }
+}
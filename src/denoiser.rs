@@ -0,0 +1,144 @@
+use crate::color::Color;
+use crate::film::Film;
+use crate::vector3::Vector3;
+
+/// 1D B3 spline taps of the à-trous wavelet transform.
+const KERNEL: [f64; 5] = [1.0 / 16.0, 1.0 / 4.0, 3.0 / 8.0, 1.0 / 4.0, 1.0 / 16.0];
+const EPSILON: f64 = 1e-3;
+
+/// Edge-avoiding à-trous wavelet filter (Dammertz et al., 2010) guided by
+/// the albedo, normal and depth of the first hits. Lighting is divided by
+/// the albedo before filtering so texture detail survives.
+#[derive(Debug, Clone, Copy)]
+pub struct Denoiser {
+    /// Filter passes, each doubling the kernel's spacing.
+    pub iterations: u32,
+    /// Tolerated lighting difference, halved every iteration.
+    pub sigma_color: f64,
+    pub sigma_normal: f64,
+    pub sigma_albedo: f64,
+    /// Tolerated depth difference relative to the depth.
+    pub sigma_depth: f64,
+}
+
+impl Denoiser {
+    pub fn new() -> Self {
+        Denoiser {
+            iterations: 5,
+            sigma_color: 1.0,
+            sigma_normal: 0.3,
+            sigma_albedo: 0.1,
+            sigma_depth: 0.05,
+        }
+    }
+
+    pub fn with_iterations(mut self, iterations: u32) -> Self {
+        self.iterations = iterations;
+        self
+    }
+
+    pub fn with_sigmas(mut self, color: f64, normal: f64, albedo: f64, depth: f64) -> Self {
+        if color <= 0.0 || normal <= 0.0 || albedo <= 0.0 || depth <= 0.0 {
+            panic!("Denoiser sigmas must be positive");
+        }
+        self.sigma_color = color;
+        self.sigma_normal = normal;
+        self.sigma_albedo = albedo;
+        self.sigma_depth = depth;
+        self
+    }
+
+    /// Filter `pixels`, the resolved image of `film`. Each of `regions`
+    /// (`x0, y0, x1, y1`, exclusive upper bounds), such as the images of a
+    /// stereo pair, is filtered without reading the others.
+    pub fn denoise(&self, film: &Film, pixels: &[Color], regions: &[(u32, u32, u32, u32)]) -> Vec<Color> {
+        let width = film.width as i64;
+        let region_of = |x: i64, y: i64| {
+            let &(x0, y0, x1, y1) = regions
+                .iter()
+                .find(|&&(x0, y0, x1, y1)| (x0 as i64..x1 as i64).contains(&x) && (y0 as i64..y1 as i64).contains(&y))
+                .expect("Denoiser regions must cover the image");
+            ((x0 as i64..x1 as i64), (y0 as i64..y1 as i64))
+        };
+        let guides: Vec<(Color, Vector3, f64)> = (0..pixels.len()).map(|k| film.guides(k)).collect();
+        let demodulate = |color: &Color, albedo: &Color| {
+            Color::new(
+                color.r() / albedo.r().max(EPSILON),
+                color.g() / albedo.g().max(EPSILON),
+                color.b() / albedo.b().max(EPSILON),
+            )
+        };
+        let mut lighting: Vec<Color> = pixels.iter().zip(&guides).map(|(color, (albedo, _, _))| demodulate(color, albedo)).collect();
+
+        for iteration in 0..self.iterations {
+            let step = 1i64 << iteration;
+            let sigma_color = self.sigma_color / (1u64 << iteration) as f64;
+            let mut filtered = Vec::with_capacity(lighting.len());
+            for y in 0..film.height as i64 {
+                for x in 0..width {
+                    let p = (y * width + x) as usize;
+                    let (columns, rows) = region_of(x, y);
+                    let (albedo_p, normal_p, depth_p) = &guides[p];
+                    let mut sum = Color::new(0.0, 0.0, 0.0);
+                    let mut weight_sum = 0.0;
+                    for (dy, ky) in KERNEL.iter().enumerate() {
+                        let qy = y + (dy as i64 - 2) * step;
+                        if !rows.contains(&qy) {
+                            continue;
+                        }
+                        for (dx, kx) in KERNEL.iter().enumerate() {
+                            let qx = x + (dx as i64 - 2) * step;
+                            if !columns.contains(&qx) {
+                                continue;
+                            }
+                            let q = (qy * width + qx) as usize;
+                            let (albedo_q, normal_q, depth_q) = &guides[q];
+                            let color_distance = distance_squared(&lighting[p], &lighting[q]);
+                            let albedo_distance = distance_squared(albedo_p, albedo_q);
+                            let normal_distance = normal_p.sub(normal_q).dot(&normal_p.sub(normal_q));
+                            let depth_distance = relative_difference(*depth_p, *depth_q);
+                            let weight = kx
+                                * ky
+                                * (-color_distance / (sigma_color * sigma_color)
+                                    - albedo_distance / (self.sigma_albedo * self.sigma_albedo)
+                                    - normal_distance / (self.sigma_normal * self.sigma_normal)
+                                    - depth_distance * depth_distance / (self.sigma_depth * self.sigma_depth))
+                                    .exp();
+                            sum = sum.add(&lighting[q].mul(weight));
+                            weight_sum += weight;
+                        }
+                    }
+                    filtered.push(sum.div(weight_sum));
+                }
+            }
+            lighting = filtered;
+        }
+
+        lighting
+            .iter()
+            .zip(&guides)
+            .map(|(light, (albedo, _, _))| {
+                Color::new(
+                    light.r() * albedo.r().max(EPSILON),
+                    light.g() * albedo.g().max(EPSILON),
+                    light.b() * albedo.b().max(EPSILON),
+                )
+            })
+            .collect()
+    }
+}
+
+fn distance_squared(a: &Color, b: &Color) -> f64 {
+    let d = a.sub(b);
+    d.r() * d.r() + d.g() * d.g() + d.b() * d.b()
+}
+
+/// Difference of two depths relative to the larger; 1 between a hit and
+/// a miss, 0 between two misses.
+fn relative_difference(a: f64, b: f64) -> f64 {
+    match (a.is_finite(), b.is_finite()) {
+        (true, true) => (a - b).abs() / a.max(b).max(EPSILON),
+        (false, false) => 0.0,
+        _ => 1.0,
+    }
+}
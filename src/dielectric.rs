@@ -1,17 +1,69 @@
 use crate::color::Color;
+use crate::ior::Ior;
 use crate::material::Material;
+use crate::lpe::Lobe;
 use crate::ray::Ray;
+use crate::spectrum::SampledWavelengths;
+use crate::thin_film::{Substrate, ThinFilm};
+use crate::utils::{absorption_from_tint, beer_lambert};
 use crate::vector3::Vector3;
 use crate::hittable::HitRecord;
-use rand::Rng;
+use crate::sampler;
 
 pub struct Dielectric {
     pub refraction_index: f64,
+    pub absorption: Vector3,
+    /// Wavelength dependent index used instead of `refraction_index` in
+    /// spectral mode.
+    pub dispersion: Option<Ior>,
+    /// Interference coating on the outer surface, replacing Schlick's
+    /// approximation with the film's reflectance.
+    pub thin_film: Option<ThinFilm>,
 }
 
 impl Dielectric {
     pub fn new(refraction_index: f64) -> Self {
-        Self { refraction_index }
+        Self {
+            refraction_index,
+            absorption: Vector3::default(),
+            dispersion: None,
+            thin_film: None,
+        }
+    }
+
+    /// Tint the interior so that light is filtered to `color` after
+    /// travelling `distance` units through the medium.
+    pub fn with_absorption(mut self, color: Color, distance: f64) -> Self {
+        if !(distance > 0.0 && distance.is_finite()) {
+            panic!("Absorption distance must be positive and finite");
+        }
+        if ![color.r(), color.g(), color.b()].iter().all(|x| x.is_finite()) {
+            panic!("Absorption tint must be finite");
+        }
+        self.absorption = absorption_from_tint(color, distance);
+        self
+    }
+
+    pub fn with_dispersion(mut self, dispersion: Ior) -> Self {
+        self.dispersion = Some(dispersion);
+        self
+    }
+
+    pub fn with_thin_film(mut self, thin_film: ThinFilm) -> Self {
+        self.thin_film = Some(thin_film);
+        self
+    }
+
+    /// Index of refraction for the ray, and the wavelengths its scattered
+    /// ray should carry. A dispersive interface splits the wavelengths, so
+    /// only the hero wavelength survives.
+    fn refraction_index_for(&self, ray_in: &Ray) -> (f64, Option<SampledWavelengths>) {
+        match (&self.dispersion, ray_in.wavelengths) {
+            (Some(dispersion), Some(wavelengths)) => {
+                (dispersion.at(wavelengths.hero()), Some(wavelengths.terminate_secondary()))
+            }
+            _ => (self.refraction_index, ray_in.wavelengths),
+        }
     }
 
     fn reflectance(cosine: f64, refraction_index: f64) -> f64 {
@@ -23,11 +75,17 @@ impl Dielectric {
 
 impl Material for Dielectric {
     fn scatter(&self, ray_in: &Ray, hit_record: &HitRecord) -> Option<(Color, Ray)> {
-        let attenuation = Color::new(1.0, 1.0, 1.0);
+        // A ray hitting the back face has travelled through the interior.
+        let mut attenuation = if hit_record.front_face {
+            Color::new(1.0, 1.0, 1.0)
+        } else {
+            beer_lambert(&self.absorption, hit_record.t * ray_in.direction.length())
+        };
+        let (refraction_index, mut wavelengths) = self.refraction_index_for(ray_in);
         let refraction_ratio = if hit_record.front_face {
-            1.0 / self.refraction_index
+            1.0 / refraction_index
         } else {
-            self.refraction_index
+            refraction_index
         };
 
         let unit_direction = ray_in.direction.normalize();
@@ -35,14 +93,37 @@ impl Material for Dielectric {
         let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
 
         let cannot_refract = refraction_ratio * sin_theta > 1.0;
-        let random_double = rand::thread_rng().gen_range(0.0..1.0);
-        let direction = if cannot_refract || Dielectric::reflectance(cos_theta, refraction_ratio) > random_double {
+        let random_double = sampler::get_1d();
+        let direction = if let Some(thin_film) = &self.thin_film {
+            // The film reflectance is colored, so reflect with its average
+            // and reweight each channel by its share.
+            let (n1, n3) = if hit_record.front_face {
+                (1.0, refraction_index)
+            } else {
+                (refraction_index, 1.0)
+            };
+            let (reflectance, film_wavelengths) =
+                thin_film.reflectance_for(ray_in, cos_theta, n1, |_| Substrate::Dielectric(n3));
+            wavelengths = film_wavelengths;
+            let probability = (reflectance.r() + reflectance.g() + reflectance.b()) / 3.0;
+            if cannot_refract {
+                Vector3::reflect(&unit_direction, &hit_record.normal)
+            } else if random_double < probability {
+                attenuation = attenuation.mul_color(&reflectance.div(probability));
+                Vector3::reflect(&unit_direction, &hit_record.normal)
+            } else {
+                let transmittance = Color::new(1.0, 1.0, 1.0).sub(&reflectance);
+                attenuation = attenuation.mul_color(&transmittance.div(1.0 - probability));
+                Vector3::refract(&unit_direction, &hit_record.normal, refraction_ratio)
+            }
+        } else if cannot_refract || Dielectric::reflectance(cos_theta, refraction_ratio) > random_double {
             Vector3::reflect(&unit_direction, &hit_record.normal)
         } else {
             Vector3::refract(&unit_direction, &hit_record.normal, refraction_ratio)
         };
 
-        let scattered = Ray::new(hit_record.point, direction);
+        let lobe = Lobe::specular_or_transmission(&direction, &hit_record.normal);
+        let scattered = Ray::new(hit_record.point, direction).with_wavelengths(wavelengths).with_lobe(lobe);
         Some((attenuation, scattered))
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,79 @@
+use std::fs::File;
+use std::io::{BufWriter, Error, Write};
+
+const MAGIC: u32 = 20000630;
+/// Single-part scanline image; bit 10 allows names longer than 31 bytes.
+const VERSION: u32 = 2;
+const LONG_NAMES: u32 = 0x400;
+const FLOAT: i32 = 2;
+
+/// Write an uncompressed OpenEXR image with one 32-bit float channel per
+/// entry of `channels`. Channel names such as `albedo.R` put channels in
+/// layers, which compositors show as separate images.
+pub fn write(path: &str, width: u32, height: u32, channels: &[(String, Vec<f32>)]) -> Result<(), Error> {
+    // Channels are stored in alphabetical order, in the header and in
+    // every scanline.
+    let mut channels: Vec<&(String, Vec<f32>)> = channels.iter().collect();
+    channels.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let mut header = Vec::new();
+    let mut channel_list = Vec::new();
+    for (name, _) in &channels {
+        channel_list.extend_from_slice(name.as_bytes());
+        channel_list.push(0);
+        channel_list.extend_from_slice(&FLOAT.to_le_bytes());
+        // pLinear and three reserved bytes, then the x and y sampling.
+        channel_list.extend_from_slice(&[0, 0, 0, 0]);
+        channel_list.extend_from_slice(&1i32.to_le_bytes());
+        channel_list.extend_from_slice(&1i32.to_le_bytes());
+    }
+    channel_list.push(0);
+    attribute(&mut header, "channels", "chlist", &channel_list);
+    attribute(&mut header, "compression", "compression", &[0]);
+    let window: Vec<u8> = [0, 0, width as i32 - 1, height as i32 - 1]
+        .iter()
+        .flat_map(|value| value.to_le_bytes())
+        .collect();
+    attribute(&mut header, "dataWindow", "box2i", &window);
+    attribute(&mut header, "displayWindow", "box2i", &window);
+    attribute(&mut header, "lineOrder", "lineOrder", &[0]);
+    attribute(&mut header, "pixelAspectRatio", "float", &1.0f32.to_le_bytes());
+    attribute(&mut header, "screenWindowCenter", "v2f", &[0; 8]);
+    attribute(&mut header, "screenWindowWidth", "float", &1.0f32.to_le_bytes());
+    header.push(0);
+
+    let long_names = channels.iter().any(|(name, _)| name.len() > 31);
+    let version = if long_names { VERSION | LONG_NAMES } else { VERSION };
+
+    let mut writer = BufWriter::new(File::create(path)?);
+    writer.write_all(&MAGIC.to_le_bytes())?;
+    writer.write_all(&version.to_le_bytes())?;
+    writer.write_all(&header)?;
+
+    // Offset table of the scanlines, each stored as its own chunk.
+    let line_size = width as u64 * channels.len() as u64 * 4;
+    let table_end = 8 + header.len() as u64 + 8 * height as u64;
+    for y in 0..height as u64 {
+        writer.write_all(&(table_end + y * (8 + line_size)).to_le_bytes())?;
+    }
+    for y in 0..height {
+        writer.write_all(&(y as i32).to_le_bytes())?;
+        writer.write_all(&(line_size as i32).to_le_bytes())?;
+        for (_, values) in &channels {
+            let start = (y * width) as usize;
+            for value in &values[start..start + width as usize] {
+                writer.write_all(&value.to_le_bytes())?;
+            }
+        }
+    }
+    writer.flush()
+}
+
+fn attribute(header: &mut Vec<u8>, name: &str, kind: &str, value: &[u8]) {
+    header.extend_from_slice(name.as_bytes());
+    header.push(0);
+    header.extend_from_slice(kind.as_bytes());
+    header.push(0);
+    header.extend_from_slice(&(value.len() as i32).to_le_bytes());
+    header.extend_from_slice(value);
+}
@@ -0,0 +1,263 @@
+use crate::adaptive::PixelStats;
+use crate::color::Color;
+use crate::filter::Filter;
+use crate::hittable::HitRecord;
+use crate::ray::Ray;
+use crate::vector3::{Point3, Vector3};
+
+/// What a camera ray first sees, guiding the denoiser and filling the
+/// geometric output layers.
+#[derive(Debug, Clone, Copy)]
+pub struct Guides {
+    /// Attenuation of the first scattering event, or the emitted or
+    /// background color where the path ends.
+    pub albedo: Color,
+    /// Shading normal in world space, zero for misses.
+    pub normal: Vector3,
+    /// Distance to the hit, `None` for misses.
+    pub depth: Option<f64>,
+    /// World-space position of the hit, the origin for misses.
+    pub position: Point3,
+    /// One plus the index of the object hit, 0 for misses and the fog.
+    pub object_id: u32,
+    /// Hash of the material hit, 0 for misses. Telling materials apart
+    /// needs no more than their address, so the IDs are only consistent
+    /// within one render.
+    pub material_id: u32,
+}
+
+impl Guides {
+    pub fn hit(ray: &Ray, hit_record: &HitRecord, albedo: Color) -> Self {
+        let address = hit_record.material as *const _ as *const () as usize as u64;
+        // Keep IDs below 2^24, where they are exact in a 32-bit float.
+        let material_id = (hash(address) % ((1 << 24) - 1)) as u32 + 1;
+        Guides {
+            albedo,
+            normal: hit_record.normal,
+            depth: Some(hit_record.t * ray.direction.length()),
+            position: hit_record.point,
+            object_id: hit_record.object_id.map_or(0, |id| id as u32 + 1),
+            material_id,
+        }
+    }
+
+    pub fn miss(background: Color) -> Self {
+        Guides {
+            albedo: background,
+            normal: Vector3::default(),
+            depth: None,
+            position: Point3::default(),
+            object_id: 0,
+            material_id: 0,
+        }
+    }
+}
+
+fn hash(mut v: u64) -> u64 {
+    v ^= v >> 33;
+    v = v.wrapping_mul(0xff51_afd7_ed55_8ccd);
+    v ^= v >> 33;
+    v = v.wrapping_mul(0xc4ce_b9fe_1a85_ec53);
+    v ^= v >> 33;
+    v
+}
+
+/// Everything one sample records besides its color.
+#[derive(Debug, Clone)]
+pub struct SampleRecord {
+    pub guides: Guides,
+    /// Contribution of every light group, the background last. Empty when
+    /// light groups are not being output.
+    pub light_groups: Vec<Color>,
+    /// Contribution of every light path expression bucket.
+    pub light_paths: Vec<Color>,
+}
+
+impl SampleRecord {
+    pub fn new(light_group_count: usize, light_path_count: usize) -> Self {
+        SampleRecord {
+            guides: Guides::miss(Color::new(0.0, 0.0, 0.0)),
+            light_groups: vec![Color::new(0.0, 0.0, 0.0); light_group_count],
+            light_paths: vec![Color::new(0.0, 0.0, 0.0); light_path_count],
+        }
+    }
+
+    /// Whether light contributions are being split into layers.
+    pub fn records_light(&self) -> bool {
+        !self.light_groups.is_empty() || !self.light_paths.is_empty()
+    }
+
+    /// Add light reaching the camera from light group `group`.
+    pub fn add_light(&mut self, group: usize, contribution: &Color) {
+        if let Some(light) = self.light_groups.get_mut(group) {
+            *light = light.add(contribution);
+        }
+    }
+
+    /// Scale everything the sample carries, as the camera ray weight does.
+    pub fn scale(&mut self, weight: f64) {
+        for light in self.light_groups.iter_mut().chain(self.light_paths.iter_mut()) {
+            *light = light.mul(weight);
+        }
+    }
+
+    /// Values of the film layers: the light groups, then the light paths.
+    pub fn layers(&self) -> Vec<Color> {
+        self.light_groups.iter().chain(&self.light_paths).copied().collect()
+    }
+}
+
+/// Frame buffer accumulating filter-weighted samples, along with the
+/// statistics of the samples taken in each pixel.
+#[derive(Debug)]
+pub struct Film {
+    pub width: u32,
+    pub height: u32,
+    pub filter: Filter,
+    pub sums: Vec<Color>,
+    pub weights: Vec<f64>,
+    pub stats: Vec<PixelStats>,
+    /// Sums of the sample guides and the number of samples that hit
+    /// something, averaged per pixel by `guides`.
+    pub albedo_sums: Vec<Color>,
+    pub normal_sums: Vec<Vector3>,
+    pub depth_sums: Vec<f64>,
+    pub position_sums: Vec<Vector3>,
+    pub hit_counts: Vec<u32>,
+    /// Object and material IDs of each pixel's first sample. IDs are not
+    /// averaged, which would blend them into IDs of other objects.
+    pub object_ids: Vec<u32>,
+    pub material_ids: Vec<u32>,
+    /// Extra images splatted with the same filter weights as the color,
+    /// such as light groups, so that they add up to it.
+    pub layer_names: Vec<String>,
+    pub layer_sums: Vec<Vec<Color>>,
+}
+
+impl Film {
+    pub fn new(width: u32, height: u32, filter: Filter) -> Self {
+        let pixel_count = (width * height) as usize;
+        Film {
+            width,
+            height,
+            filter,
+            sums: vec![Color::new(0.0, 0.0, 0.0); pixel_count],
+            weights: vec![0.0; pixel_count],
+            stats: vec![PixelStats::new(); pixel_count],
+            albedo_sums: vec![Color::new(0.0, 0.0, 0.0); pixel_count],
+            normal_sums: vec![Vector3::default(); pixel_count],
+            depth_sums: vec![0.0; pixel_count],
+            position_sums: vec![Vector3::default(); pixel_count],
+            hit_counts: vec![0; pixel_count],
+            object_ids: vec![0; pixel_count],
+            material_ids: vec![0; pixel_count],
+            layer_names: Vec::new(),
+            layer_sums: Vec::new(),
+        }
+    }
+
+    /// Add empty layers with the given names.
+    pub fn with_layers(mut self, names: &[String]) -> Self {
+        let pixel_count = self.sums.len();
+        for name in names {
+            self.layer_names.push(name.clone());
+            self.layer_sums.push(vec![Color::new(0.0, 0.0, 0.0); pixel_count]);
+        }
+        self
+    }
+
+    /// Add the guides of a sample taken in pixel `k`, before the sample
+    /// is counted in `stats`.
+    pub fn add_guides(&mut self, k: usize, guides: &Guides) {
+        if self.stats[k].count == 0 {
+            self.object_ids[k] = guides.object_id;
+            self.material_ids[k] = guides.material_id;
+        }
+        self.albedo_sums[k] = self.albedo_sums[k].add(&guides.albedo);
+        if let Some(depth) = guides.depth {
+            self.normal_sums[k] = self.normal_sums[k].add(&guides.normal);
+            self.depth_sums[k] += depth;
+            self.position_sums[k] = self.position_sums[k].add(&guides.position);
+            self.hit_counts[k] += 1;
+        }
+    }
+
+    /// Average position of the hits in pixel `k`, the origin if none.
+    pub fn position(&self, k: usize) -> Point3 {
+        self.position_sums[k].div(self.hit_counts[k].max(1) as f64)
+    }
+
+    /// Average albedo, unit normal and depth of pixel `k`. Pixels whose
+    /// samples all missed have a zero normal and infinite depth.
+    pub fn guides(&self, k: usize) -> (Color, Vector3, f64) {
+        let albedo = self.albedo_sums[k].div(self.stats[k].count.max(1) as f64);
+        if self.hit_counts[k] == 0 {
+            return (albedo, Vector3::default(), f64::INFINITY);
+        }
+        let normal = self.normal_sums[k];
+        let normal = if normal.near_zero() { normal } else { normal.normalize() };
+        (albedo, normal, self.depth_sums[k] / self.hit_counts[k] as f64)
+    }
+
+    pub fn sample_count(&self) -> u64 {
+        self.stats.iter().map(|pixel| pixel.count as u64).sum()
+    }
+
+    /// Mean relative error of the pixels, an estimate of the noise left in
+    /// the whole image.
+    pub fn relative_error(&self) -> f64 {
+        self.stats.iter().map(|pixel| pixel.relative_error()).sum::<f64>() / self.stats.len() as f64
+    }
+
+    /// Splat a sample taken at continuous image position `(x, y)` into
+    /// every pixel whose filter covers it. Only pixels inside `region`
+    /// (`x0, y0, x1, y1`, exclusive upper bounds) receive it, keeping the
+    /// images of a stereo pair apart. `layers` holds the sample's value in
+    /// each of the film's layers.
+    pub fn add_sample(&mut self, x: f64, y: f64, color: &Color, layers: &[Color], region: (u32, u32, u32, u32)) {
+        let radius = self.filter.radius();
+        let (x0, y0, x1, y1) = region;
+        let first_i = ((x - radius - 0.5).ceil().max(x0 as f64)) as u32;
+        let last_i = ((x + radius - 0.5).floor().min(x1 as f64 - 1.0)) as i64;
+        let first_j = ((y - radius - 0.5).ceil().max(y0 as f64)) as u32;
+        let last_j = ((y + radius - 0.5).floor().min(y1 as f64 - 1.0)) as i64;
+        for j in first_j as i64..=last_j {
+            for i in first_i as i64..=last_i {
+                let weight = self.filter.evaluate(x - (i as f64 + 0.5), y - (j as f64 + 0.5));
+                if weight == 0.0 {
+                    continue;
+                }
+                let k = (j as u32 * self.width + i as u32) as usize;
+                self.sums[k] = self.sums[k].add(&color.mul(weight));
+                self.weights[k] += weight;
+                for (sums, value) in self.layer_sums.iter_mut().zip(layers) {
+                    sums[k] = sums[k].add(&value.mul(weight));
+                }
+            }
+        }
+    }
+
+    /// Weighted average of every pixel. Negative filter lobes can push a
+    /// pixel below zero, which is clipped to black.
+    pub fn resolve(&self) -> Vec<Color> {
+        self.resolve_sums(&self.sums)
+    }
+
+    /// Weighted average of every pixel of layer `index`.
+    pub fn resolve_layer(&self, index: usize) -> Vec<Color> {
+        self.resolve_sums(&self.layer_sums[index])
+    }
+
+    fn resolve_sums(&self, sums: &[Color]) -> Vec<Color> {
+        sums.iter()
+            .zip(&self.weights)
+            .map(|(sum, &weight)| {
+                if weight <= 0.0 {
+                    return Color::new(0.0, 0.0, 0.0);
+                }
+                let pixel = sum.div(weight);
+                Color::new(pixel.r().max(0.0), pixel.g().max(0.0), pixel.b().max(0.0))
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,59 @@
+use std::f64::consts::PI;
+
+/// Pixel reconstruction filter, separable in x and y. `radius` is in
+/// pixels.
+#[derive(Debug, Clone, Copy)]
+pub enum Filter {
+    Box { radius: f64 },
+    Tent { radius: f64 },
+    /// Gaussian shifted down so it reaches zero at the radius.
+    Gaussian { radius: f64, sigma: f64 },
+    /// Mitchell–Netravali cubic, `b = c = 1/3` being the usual choice.
+    Mitchell { radius: f64, b: f64, c: f64 },
+    BlackmanHarris { radius: f64 },
+}
+
+impl Filter {
+    pub fn radius(&self) -> f64 {
+        match *self {
+            Filter::Box { radius }
+            | Filter::Tent { radius }
+            | Filter::Gaussian { radius, .. }
+            | Filter::Mitchell { radius, .. }
+            | Filter::BlackmanHarris { radius } => radius,
+        }
+    }
+
+    /// Weight of a sample at offset `(x, y)` from a pixel centre.
+    pub fn evaluate(&self, x: f64, y: f64) -> f64 {
+        self.evaluate_1d(x) * self.evaluate_1d(y)
+    }
+
+    fn evaluate_1d(&self, x: f64) -> f64 {
+        let x = x.abs();
+        let radius = self.radius();
+        if x > radius {
+            return 0.0;
+        }
+        match *self {
+            Filter::Box { .. } => 1.0,
+            Filter::Tent { .. } => radius - x,
+            Filter::Gaussian { sigma, .. } => {
+                let gaussian = |x: f64| (-x * x / (2.0 * sigma * sigma)).exp();
+                (gaussian(x) - gaussian(radius)).max(0.0)
+            }
+            Filter::Mitchell { b, c, .. } => {
+                let x = 2.0 * x / radius;
+                if x > 1.0 {
+                    ((-b - 6.0 * c) * x.powi(3) + (6.0 * b + 30.0 * c) * x * x + (-12.0 * b - 48.0 * c) * x + (8.0 * b + 24.0 * c)) / 6.0
+                } else {
+                    ((12.0 - 9.0 * b - 6.0 * c) * x.powi(3) + (-18.0 + 12.0 * b + 6.0 * c) * x * x + (6.0 - 2.0 * b)) / 6.0
+                }
+            }
+            Filter::BlackmanHarris { .. } => {
+                let t = 0.5 + 0.5 * x / radius;
+                0.35875 - 0.48829 * (2.0 * PI * t).cos() + 0.14128 * (4.0 * PI * t).cos() - 0.01168 * (6.0 * PI * t).cos()
+            }
+        }
+    }
+}
@@ -0,0 +1,51 @@
+use crate::hittable::HitRecord;
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::vector3::Vector3;
+use crate::sampler;
+
+/// Homogeneous fog filling the whole scene. Rays that escape to the sky are
+/// only fogged for `extent` units so thin fog still lets the sky through.
+pub struct Fog {
+    density: f64,
+    extent: f64,
+    phase_function: Box<dyn Material>,
+}
+
+impl Fog {
+    pub fn new(density: f64, extent: f64, phase_function: Box<dyn Material>) -> Self {
+        if density <= 0.0 {
+            panic!("Density must be positive");
+        }
+        Fog {
+            density,
+            extent,
+            phase_function,
+        }
+    }
+
+    /// Sample a scattering event along the ray before `ray_tmax`, and
+    /// before the fog extent if the ray `escapes` without hitting anything.
+    pub fn hit(&self, ray: &Ray, ray_tmin: f64, ray_tmax: f64, escapes: bool) -> Option<HitRecord<'_>> {
+        let ray_length = ray.direction.length();
+        let t_max = if escapes {
+            ray_tmax.min(self.extent / ray_length)
+        } else {
+            ray_tmax
+        };
+        let hit_distance = -sampler::get_1d().ln() / self.density;
+        let t = ray_tmin + hit_distance / ray_length;
+        if t >= t_max {
+            return None;
+        }
+        Some(HitRecord::new(
+            ray.at(t),
+            Vector3::new(1.0, 0.0, 0.0),
+            t,
+            0.0,
+            0.0,
+            ray.direction,
+            &*self.phase_function,
+        ))
+    }
+}
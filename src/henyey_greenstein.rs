@@ -0,0 +1,48 @@
+use std::f64::consts::PI;
+use crate::color::Color;
+use crate::material::Material;
+use crate::onb::Onb;
+use crate::lpe::Lobe;
+use crate::ray::Ray;
+use crate::vector3::Vector3;
+use crate::hittable::HitRecord;
+use crate::sampler;
+
+/// Henyey-Greenstein phase function for media. Positive `g` scatters
+/// forward (haze, god rays), negative `g` scatters back.
+pub struct HenyeyGreenstein {
+    albedo: Color,
+    g: f64,
+}
+
+impl HenyeyGreenstein {
+    pub fn new(albedo: Color, g: f64) -> Self {
+        if g <= -1.0 || g >= 1.0 {
+            panic!("Asymmetry must be between -1.0 and 1.0");
+        }
+        HenyeyGreenstein { albedo, g }
+    }
+
+    /// Sample the cosine between the incoming propagation direction and the
+    /// scattered direction.
+    fn sample_cos_theta(&self, u: f64) -> f64 {
+        if self.g.abs() < 1e-3 {
+            return 1.0 - 2.0 * u;
+        }
+        let g = self.g;
+        let s = (1.0 - g * g) / (1.0 - g + 2.0 * g * u);
+        ((1.0 + g * g - s * s) / (2.0 * g)).clamp(-1.0, 1.0)
+    }
+}
+
+impl Material for HenyeyGreenstein {
+    fn scatter(&self, ray_in: &Ray, hit_record: &HitRecord) -> Option<(Color, Ray)> {
+        let (u1, u2) = sampler::get_2d();
+        let cos_theta = self.sample_cos_theta(u1);
+        let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+        let phi = 2.0 * PI * u2;
+        let local = Vector3::new(sin_theta * phi.cos(), sin_theta * phi.sin(), cos_theta);
+        let direction = Onb::new(&ray_in.direction).to_world(&local);
+        Some((self.albedo, Ray::new(hit_record.point, direction).with_lobe(Lobe::Volume)))
+    }
+}
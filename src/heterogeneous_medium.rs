@@ -0,0 +1,132 @@
+use crate::aabb::Aabb;
+use crate::color::Color;
+use crate::hittable::{HitRecord, Hittable};
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::spectrum::blackbody;
+use crate::vector3::{Point3, Vector3};
+use crate::voxel_grid::VoxelGrid;
+use crate::sampler;
+
+/// Blackbody emission driven by a temperature grid, for fire.
+struct Emission {
+    temperature: VoxelGrid,
+    temperature_scale: f64,
+    intensity: f64,
+}
+
+/// Phase function of the medium plus its optional emission.
+struct MediumMaterial {
+    bounds: Aabb,
+    phase_function: Box<dyn Material>,
+    emission: Option<Emission>,
+}
+
+impl Material for MediumMaterial {
+    fn scatter(&self, ray_in: &Ray, hit_record: &HitRecord) -> Option<(Color, Ray)> {
+        self.phase_function.scatter(ray_in, hit_record)
+    }
+
+    /// Collision estimator for emission: every real collision picks up the
+    /// emitted radiance weighted by the absorbing fraction of the extinction.
+    /// Both scale with density, so `intensity` carries that ratio.
+    fn emitted(&self, hit_record: &HitRecord) -> Color {
+        match &self.emission {
+            Some(emission) => {
+                let local = to_grid_space(&self.bounds, &hit_record.point);
+                let temperature = emission.temperature.lookup(&local) * emission.temperature_scale;
+                blackbody(temperature).mul(emission.intensity)
+            }
+            None => Color::new(0.0, 0.0, 0.0),
+        }
+    }
+}
+
+fn to_grid_space(bounds: &Aabb, point: &Point3) -> Point3 {
+    let size = bounds.size();
+    let offset = point.sub(&bounds.min);
+    Point3::new(offset.x / size.x, offset.y / size.y, offset.z / size.z)
+}
+
+/// Participating medium whose density comes from a voxel grid stretched over
+/// an axis-aligned box. Collisions are sampled with delta tracking against
+/// the grid's maximum density.
+pub struct HeterogeneousMedium {
+    density: VoxelGrid,
+    density_scale: f64,
+    majorant: f64,
+    material: MediumMaterial,
+}
+
+impl HeterogeneousMedium {
+    pub fn new(bounds: Aabb, density: VoxelGrid, density_scale: f64, phase_function: Box<dyn Material>) -> Self {
+        if density_scale <= 0.0 {
+            panic!("Density scale must be positive");
+        }
+        let majorant = density.max_value() * density_scale;
+        HeterogeneousMedium {
+            density,
+            density_scale,
+            majorant,
+            material: MediumMaterial {
+                bounds,
+                phase_function,
+                emission: None,
+            },
+        }
+    }
+
+    /// Make the medium glow with the blackbody color of a temperature grid
+    /// covering the same bounds. Grid values are multiplied by
+    /// `temperature_scale` to get kelvin.
+    pub fn with_emission(mut self, temperature: VoxelGrid, temperature_scale: f64, intensity: f64) -> Self {
+        self.material.emission = Some(Emission {
+            temperature,
+            temperature_scale,
+            intensity,
+        });
+        self
+    }
+
+    fn density_at(&self, point: &Point3) -> f64 {
+        let local = to_grid_space(&self.material.bounds, point);
+        self.density.lookup(&local) * self.density_scale
+    }
+}
+
+impl Hittable for HeterogeneousMedium {
+    fn hit(&self, ray: &Ray, ray_tmin: f64, ray_tmax: f64) -> Option<HitRecord<'_>> {
+        let (t_enter, t_exit) = self.material.bounds.hit(ray, ray_tmin, ray_tmax)?;
+        if self.majorant <= 0.0 {
+            return None;
+        }
+
+        // Delta tracking: step through the homogenized medium and accept a
+        // tentative collision as real with probability density / majorant.
+        let ray_length = ray.direction.length();
+        let mut t = t_enter;
+        loop {
+            t -= (1.0 - sampler::get_1d()).ln() / (self.majorant * ray_length);
+            if t >= t_exit {
+                return None;
+            }
+            let point = ray.at(t);
+            if sampler::get_1d() * self.majorant < self.density_at(&point) {
+                // The normal is arbitrary inside a medium.
+                return Some(HitRecord::new(
+                    point,
+                    Vector3::new(1.0, 0.0, 0.0),
+                    t,
+                    0.0,
+                    0.0,
+                    ray.direction,
+                    &self.material,
+                ));
+            }
+        }
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.material.bounds
+    }
+}
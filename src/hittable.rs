@@ -1,33 +1,106 @@
+use crate::aabb::Aabb;
+use crate::onb::Onb;
 use crate::ray::Ray;
 use crate::vector3::{Point3, Vector3};
 use crate::material::Material;
 
+#[derive(Clone, Copy)]
 pub struct HitRecord<'a> {
     pub point: Point3,
+    /// Shading normal, facing against the incoming ray.
     pub normal: Vector3,
+    /// True surface normal, facing the same side as `normal`.
+    pub geometric_normal: Vector3,
+    /// Partial derivatives of the surface point along `u` and `v`, oriented
+    /// with the outward normal. Not normalized.
+    pub tangent: Vector3,
+    pub bitangent: Vector3,
     pub t: f64,
+    pub u: f64,
+    pub v: f64,
     pub front_face: bool,
     pub material: &'a dyn Material,
+    /// Index of the scene object hit, set by `Hittables`. `None` for
+    /// scattering in the scene fog.
+    pub object_id: Option<usize>,
+    /// The scene object hit, set by `Hittables` along with `object_id`,
+    /// for materials that trace further rays against their own surface.
+    pub object: Option<&'a dyn Hittable>,
 }
 
 impl<'a> HitRecord<'a> {
-    pub fn new(point: Point3, outward_normal: Vector3, t: f64, ray_direction: Vector3, material: &'a dyn Material) -> Self {
+    /// Create a record with an arbitrary tangent frame around the normal;
+    /// surfaces with texture coordinates set theirs with `with_tangents`.
+    pub fn new(
+        point: Point3,
+        outward_normal: Vector3,
+        t: f64,
+        u: f64,
+        v: f64,
+        ray_direction: Vector3,
+        material: &'a dyn Material,
+    ) -> Self {
         let front_face = ray_direction.dot(&outward_normal) < 0.0;
         let normal = if front_face {
             outward_normal
         } else {
             outward_normal.neg()
         };
+        let onb = Onb::new(&outward_normal);
         HitRecord {
             point,
             normal,
+            geometric_normal: normal,
+            tangent: onb.u,
+            bitangent: onb.v,
             t,
+            u,
+            v,
             front_face,
             material,
+            object_id: None,
+            object: None,
         }
     }
+
+    pub fn with_tangents(mut self, tangent: Vector3, bitangent: Vector3) -> Self {
+        self.tangent = tangent;
+        self.bitangent = bitangent;
+        self
+    }
+
+    /// The outward facing shading normal, regardless of which side was hit.
+    pub fn outward_normal(&self) -> Vector3 {
+        if self.front_face {
+            self.normal
+        } else {
+            self.normal.neg()
+        }
+    }
+
+    /// Copy of the record shaded with a perturbed outward normal. The
+    /// perturbation is dropped if it would face away from `ray_direction`.
+    pub fn with_shading_normal(&self, outward_normal: Vector3, ray_direction: &Vector3) -> Self {
+        let normal = if self.front_face {
+            outward_normal
+        } else {
+            outward_normal.neg()
+        };
+        if normal.dot(ray_direction) >= 0.0 {
+            return *self;
+        }
+        HitRecord { normal, ..*self }
+    }
+
+    /// Whether a scattered direction lies on the same side of both the
+    /// shading and the geometric normal. Directions that don't would leak
+    /// light through the surface.
+    pub fn is_consistent(&self, direction: &Vector3) -> bool {
+        (direction.dot(&self.normal) > 0.0) == (direction.dot(&self.geometric_normal) > 0.0)
+    }
 }
 
 pub trait Hittable {
-    fn hit(&self, ray: &Ray, ray_tmin: f64, ray_tmax: f64) -> Option<HitRecord>;
+    fn hit(&self, ray: &Ray, ray_tmin: f64, ray_tmax: f64) -> Option<HitRecord<'_>>;
+    fn bounding_box(&self) -> Aabb;
 }
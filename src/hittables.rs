@@ -1,32 +1,108 @@
+use crate::aabb::Aabb;
+use crate::fog::Fog;
 use crate::hittable::{HitRecord, Hittable};
 use crate::ray::Ray;
+use crate::sampler;
 
 pub struct Hittables {
     pub hittables: Vec<Box<dyn Hittable>>,
+    pub fog: Option<Fog>,
+    /// Light group of every object, indexing `light_group_names`.
+    pub light_groups: Vec<usize>,
+    /// Names of the light groups, starting with the default group that
+    /// objects and the fog belong to unless added to another.
+    pub light_group_names: Vec<String>,
 }
 
 impl Hittables {
     pub fn new() -> Self {
         Hittables {
             hittables: Vec::new(),
+            fog: None,
+            light_groups: Vec::new(),
+            light_group_names: vec!["default".to_string()],
         }
     }
 
     pub fn add(&mut self, hittable: Box<dyn Hittable>) {
         self.hittables.push(hittable);
+        self.light_groups.push(0);
     }
 
-    pub fn hit(&self, ray: &Ray, ray_tmin: f64, ray_tmax: f64) -> Option<HitRecord> {
+    /// Add an object whose emission is collected in the light group `name`,
+    /// creating the group on first use.
+    pub fn add_to_light_group(&mut self, hittable: Box<dyn Hittable>, name: &str) {
+        let group = match self.light_group_names.iter().position(|group| group == name) {
+            Some(group) => group,
+            None => {
+                self.light_group_names.push(name.to_string());
+                self.light_group_names.len() - 1
+            }
+        };
+        self.hittables.push(hittable);
+        self.light_groups.push(group);
+    }
+
+    /// Light group of the object with index `object_id`.
+    pub fn light_group(&self, object_id: Option<usize>) -> usize {
+        object_id.map_or(0, |id| self.light_groups[id])
+    }
+
+    pub fn set_fog(&mut self, fog: Fog) {
+        self.fog = Some(fog);
+    }
+
+    /// Closest surface hit, or a scattering event in the scene fog if one
+    /// happens before the ray reaches that surface.
+    pub fn hit(&self, ray: &Ray, ray_tmin: f64, ray_tmax: f64) -> Option<HitRecord<'_>> {
+        let surface_hit = self.hit_surfaces(ray, ray_tmin, ray_tmax);
+        if let Some(fog) = &self.fog {
+            let t_max = surface_hit.as_ref().map_or(ray_tmax, |hit| hit.t);
+            if let Some(fog_hit) = fog.hit(ray, ray_tmin, t_max, surface_hit.is_none()) {
+                return Some(fog_hit);
+            }
+        }
+        surface_hit
+    }
+
+    /// Box enclosing every scene object, or `None` for an empty scene. The
+    /// fog is unbounded and not included.
+    pub fn bounding_box(&self) -> Option<Aabb> {
+        self.hittables
+            .iter()
+            .map(|hittable| hittable.bounding_box())
+            .reduce(|a, b| a.union(&b))
+    }
+
+    /// Closest hit against the scene objects, ignoring the fog.
+    pub fn hit_surfaces(&self, ray: &Ray, ray_tmin: f64, ray_tmax: f64) -> Option<HitRecord<'_>> {
         let mut hit_record = None;
         let mut closest_so_far = ray_tmax;
 
-        for hittable in self.hittables.iter() {
-            if let Some(record) = hittable.hit(ray, ray_tmin, closest_so_far) {
+        for (index, hittable) in self.hittables.iter().enumerate() {
+            if let Some(mut record) = Hittables::hit_opaque(&**hittable, ray, ray_tmin, closest_so_far) {
                 closest_so_far = record.t;
+                record.object_id = Some(index);
+                record.object = Some(&**hittable);
                 hit_record = Some(record);
             }
         }
 
         hit_record
     }
+
+    /// Closest hit on `hittable` that survives its material's opacity.
+    /// Fractional opacity is resolved stochastically, continuing the search
+    /// behind each rejected hit.
+    fn hit_opaque<'a>(hittable: &'a dyn Hittable, ray: &Ray, ray_tmin: f64, ray_tmax: f64) -> Option<HitRecord<'a>> {
+        let mut t_min = ray_tmin;
+        loop {
+            let record = hittable.hit(ray, t_min, ray_tmax)?;
+            let opacity = record.material.opacity(&record);
+            if opacity >= 1.0 || sampler::get_1d() < opacity {
+                return Some(record);
+            }
+            t_min = record.t;
+        }
+    }
 }
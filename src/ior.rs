@@ -0,0 +1,40 @@
+/// Wavelength dependent index of refraction. Wavelengths are in nanometres.
+#[derive(Debug, Clone, Copy)]
+pub enum Ior {
+    Constant(f64),
+    /// Cauchy's equation `a + b / λ²` with λ in micrometres.
+    Cauchy { a: f64, b: f64 },
+    /// Sellmeier equation with coefficients for λ in micrometres.
+    Sellmeier { b: [f64; 3], c: [f64; 3] },
+}
+
+impl Ior {
+    /// Schott N-BK7 crown glass.
+    pub fn bk7() -> Self {
+        Ior::Sellmeier {
+            b: [1.03961212, 0.231792344, 1.01046945],
+            c: [0.00600069867, 0.0200179144, 103.560653],
+        }
+    }
+
+    /// Diamond, which disperses much more strongly than glass.
+    pub fn diamond() -> Self {
+        Ior::Sellmeier {
+            b: [0.3306, 4.3356, 0.0],
+            c: [0.030625, 0.011236, 0.0],
+        }
+    }
+
+    pub fn at(&self, lambda: f64) -> f64 {
+        let micrometres = lambda / 1000.0;
+        let l2 = micrometres * micrometres;
+        match *self {
+            Ior::Constant(n) => n,
+            Ior::Cauchy { a, b } => a + b / l2,
+            Ior::Sellmeier { b, c } => {
+                let n2 = 1.0 + b.iter().zip(c.iter()).map(|(b, c)| b * l2 / (l2 - c)).sum::<f64>();
+                n2.sqrt()
+            }
+        }
+    }
+}
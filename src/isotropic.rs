@@ -0,0 +1,24 @@
+use crate::color::Color;
+use crate::material::Material;
+use crate::lpe::Lobe;
+use crate::ray::Ray;
+use crate::vector3::Vector3;
+use crate::hittable::HitRecord;
+
+/// Phase function scattering uniformly in all directions, for media.
+pub struct Isotropic {
+    albedo: Color,
+}
+
+impl Isotropic {
+    pub fn new(albedo: Color) -> Self {
+        Isotropic { albedo }
+    }
+}
+
+impl Material for Isotropic {
+    fn scatter(&self, _ray_in: &Ray, hit_record: &HitRecord) -> Option<(Color, Ray)> {
+        let scattered = Ray::new(hit_record.point, Vector3::random_unit_vector()).with_lobe(Lobe::Volume);
+        Some((self.albedo, scattered))
+    }
+}
@@ -1,5 +1,6 @@
 use crate::color::Color;
 use crate::material::Material;
+use crate::lpe::Lobe;
 use crate::ray::Ray;
 use crate::vector3::Vector3;
 use crate::hittable::HitRecord;
@@ -20,7 +21,7 @@ impl Material for Lambertian {
         if scatter_direction.near_zero() {
             scatter_direction = hit_record.normal;
         }
-        let scattered = Ray::new(hit_record.point, scatter_direction);
+        let scattered = Ray::new(hit_record.point, scatter_direction).with_lobe(Lobe::Diffuse);
         Some((self.albedo, scattered))
     }
 }
@@ -0,0 +1,315 @@
+use std::fs;
+use std::io::{Error, ErrorKind};
+use crate::ray::Ray;
+use crate::vector3::{Point3, Vector3};
+
+/// One refracting surface (or the aperture stop) of a lens prescription.
+#[derive(Debug, Clone, Copy)]
+pub struct LensElement {
+    /// Radius of curvature in mm, positive when the centre lies toward the
+    /// film. Zero marks the aperture stop.
+    pub curvature_radius: f64,
+    /// Distance to the next surface toward the film, in mm.
+    pub thickness: f64,
+    /// Index of refraction of the medium behind this surface.
+    pub refraction_index: f64,
+    pub aperture_radius: f64,
+}
+
+impl LensElement {
+    fn is_stop(&self) -> bool {
+        self.curvature_radius == 0.0
+    }
+}
+
+/// Thick lens camera traced through actual glass elements, giving natural
+/// vignetting, distortion and bokeh shaped by the element apertures.
+///
+/// Lens space has the film in the `z = 0` plane and the elements along
+/// negative z, with the scene beyond them. Lengths are in millimetres.
+#[derive(Debug)]
+pub struct LensSystem {
+    elements: Vec<LensElement>,
+    film_diagonal: f64,
+    film_width: f64,
+    film_height: f64,
+    /// Scene units per millimetre.
+    units_per_mm: f64,
+    /// Scale making the film centre as bright as an unobstructed pinhole.
+    exposure: f64,
+    focal_length: f64,
+    /// Axial positions of the front and rear principal planes relative to
+    /// the rear vertex, which stay put as the film moves to focus.
+    front_principal: f64,
+    rear_principal: f64,
+}
+
+impl LensSystem {
+    /// Load a prescription with one `radius thickness ior aperture_diameter`
+    /// line per surface, ordered from the scene to the film. Lines starting
+    /// with `#` are comments. Prescriptions that cannot form an image at
+    /// the film centre are rejected.
+    pub fn load(path: &str, film_diagonal: f64) -> Result<Self, Error> {
+        let text = fs::read_to_string(path)?;
+        let mut elements = Vec::new();
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let values = line
+                .split_whitespace()
+                .map(|token| token.parse::<f64>())
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|e| Error::new(ErrorKind::InvalidData, format!("{}:{}: {}", path, number + 1, e)))?;
+            if values.len() != 4 {
+                return Err(Error::new(
+                    ErrorKind::InvalidData,
+                    format!("{}:{}: expected 4 values, got {}", path, number + 1, values.len()),
+                ));
+            }
+            elements.push(LensElement {
+                curvature_radius: values[0],
+                thickness: values[1],
+                refraction_index: if values[2] == 0.0 { 1.0 } else { values[2] },
+                aperture_radius: values[3] / 2.0,
+            });
+        }
+        if elements.is_empty() {
+            return Err(Error::new(ErrorKind::InvalidData, format!("{}: no lens elements", path)));
+        }
+        let mut system = LensSystem {
+            elements,
+            film_diagonal,
+            film_width: 0.0,
+            film_height: 0.0,
+            units_per_mm: 0.001,
+            exposure: 1.0,
+            focal_length: 0.0,
+            front_principal: 0.0,
+            rear_principal: 0.0,
+        };
+        let invalid = |message: &str| Error::new(ErrorKind::InvalidData, format!("{}: {}", path, message));
+        let Some((focal_length, front_principal, rear_principal)) = system.thick_lens() else {
+            return Err(invalid("paraxial rays cannot pass through the lens system"));
+        };
+        if focal_length <= 0.0 {
+            return Err(invalid("lens system does not converge light"));
+        }
+        // Start with the film in focus at infinity.
+        let back_focal_length = rear_principal + focal_length;
+        if back_focal_length <= 0.0 {
+            return Err(invalid("focal plane lies inside the lens system"));
+        }
+        system.elements.last_mut().unwrap().thickness = back_focal_length;
+        if system.center_weight() <= 0.0 {
+            return Err(invalid("lens system lets no light reach the film centre"));
+        }
+        system.focal_length = focal_length;
+        system.front_principal = front_principal;
+        system.rear_principal = rear_principal;
+        Ok(system)
+    }
+
+    /// Scale between scene units and millimetres, 0.001 (metres) by default.
+    pub fn with_units_per_mm(mut self, units_per_mm: f64) -> Self {
+        self.units_per_mm = units_per_mm;
+        self
+    }
+
+    /// Stop the aperture down (or open it up) to `diameter` mm.
+    pub fn with_aperture_diameter(mut self, diameter: f64) -> Self {
+        if diameter <= 0.0 {
+            panic!("Aperture diameter must be positive");
+        }
+        for element in self.elements.iter_mut().filter(|element| element.is_stop()) {
+            element.aperture_radius = diameter / 2.0;
+        }
+        self
+    }
+
+    /// Nearest distance from the film, in scene units, that the lens can
+    /// bring into focus.
+    pub fn min_focus_distance(&self) -> f64 {
+        (4.0 * self.focal_length + self.rear_principal - self.front_principal) * self.units_per_mm
+    }
+
+    /// Size the film for the image aspect ratio and move it so that the
+    /// plane `focus_distance` scene units away is in focus. Panics if it is
+    /// nearer than `min_focus_distance`.
+    pub fn configure(&mut self, aspect_ratio: f64, focus_distance: f64) {
+        self.film_height = self.film_diagonal / (1.0 + aspect_ratio * aspect_ratio).sqrt();
+        self.film_width = self.film_height * aspect_ratio;
+        self.focus(focus_distance / self.units_per_mm);
+        // A stop closed down until it blocks the film centre leaves the
+        // image black whatever the exposure.
+        let weight = self.center_weight();
+        self.exposure = if weight > 0.0 { 1.0 / weight } else { 1.0 };
+    }
+
+    /// Ray leaving the front element for film position `(s, t)` in `[0, 1]`
+    /// (image left to right, top to bottom), in camera space scene units
+    /// (x right, y up, z forward), with its radiometric weight. Rays blocked
+    /// by an element return `None`.
+    pub fn generate_ray(&self, s: f64, t: f64) -> Option<(Ray, f64)> {
+        // The lens inverts the image, so the top left pixel sits at the
+        // bottom right of the film.
+        let film_point = Point3::new((0.5 - s) * self.film_width, (t - 0.5) * self.film_height, 0.0);
+        let (ray, weight) = self.sample_film_ray(&film_point)?;
+        let origin = Vector3::new(ray.origin.x, ray.origin.y, -ray.origin.z).mul(self.units_per_mm);
+        let direction = Vector3::new(ray.direction.x, ray.direction.y, -ray.direction.z);
+        Some((Ray::new(origin, direction), weight * self.exposure))
+    }
+
+    /// Trace from a film point toward a uniform sample on the rear element.
+    /// The weight is the cos⁴ falloff of the ray leaving the film.
+    fn sample_film_ray(&self, film_point: &Point3) -> Option<(Ray, f64)> {
+        self.film_ray(film_point, &Vector3::random_in_unit_disk())
+    }
+
+    /// Trace from a film point toward the rear element point at `disk`, a
+    /// point of the unit disk.
+    fn film_ray(&self, film_point: &Point3, disk: &Vector3) -> Option<(Ray, f64)> {
+        let rear = self.elements.last().unwrap();
+        let p = disk.mul(rear.aperture_radius);
+        let rear_point = Point3::new(p.x, p.y, -rear.thickness);
+        let direction = rear_point.sub(film_point).normalize();
+        let ray = self.trace_from_film(&Ray::new(*film_point, direction))?;
+        let cos_theta = direction.z.abs();
+        Some((ray, cos_theta.powi(4)))
+    }
+
+    /// Average weight of rays from the film centre, over a fixed spiral of
+    /// points evenly covering the rear element so the exposure is repeatable.
+    fn center_weight(&self) -> f64 {
+        let samples = 4096;
+        let golden_angle = std::f64::consts::PI * (3.0 - 5f64.sqrt());
+        let total: f64 = (0..samples)
+            .filter_map(|k| {
+                let r = ((k as f64 + 0.5) / samples as f64).sqrt();
+                let theta = k as f64 * golden_angle;
+                self.film_ray(&Point3::default(), &Vector3::new(r * theta.cos(), r * theta.sin(), 0.0))
+            })
+            .map(|(_, weight)| weight)
+            .sum();
+        total / samples as f64
+    }
+
+    fn front_z(&self) -> f64 {
+        self.elements.iter().map(|element| element.thickness).sum()
+    }
+
+    /// Intersect a spherical surface centred on the axis at `z_center`.
+    /// Returns the distance along the ray and the normal facing the ray.
+    fn intersect_spherical(radius: f64, z_center: f64, ray: &Ray) -> Option<(f64, Vector3)> {
+        let o = ray.origin.sub(&Vector3::new(0.0, 0.0, z_center));
+        let a = ray.direction.dot(&ray.direction);
+        let b = 2.0 * ray.direction.dot(&o);
+        let c = o.dot(&o) - radius * radius;
+        let discriminant = b * b - 4.0 * a * c;
+        if discriminant < 0.0 {
+            return None;
+        }
+        let sqrtd = discriminant.sqrt();
+        let t0 = (-b - sqrtd) / (2.0 * a);
+        let t1 = (-b + sqrtd) / (2.0 * a);
+        let use_closer = (ray.direction.z > 0.0) ^ (radius < 0.0);
+        let t = if use_closer { t0.min(t1) } else { t0.max(t1) };
+        if t < 0.0 {
+            return None;
+        }
+        let mut normal = o.add(&ray.direction.mul(t)).normalize();
+        if normal.dot(&ray.direction) > 0.0 {
+            normal = normal.neg();
+        }
+        Some((t, normal))
+    }
+
+    /// Cross element `element` at axial position `z`, refracting from
+    /// `eta_i` into `eta_t`. Returns `None` if the ray misses the aperture
+    /// or is totally internally reflected.
+    fn cross(element: &LensElement, z: f64, ray: &Ray, eta_i: f64, eta_t: f64) -> Option<Ray> {
+        if element.is_stop() {
+            let t = (z - ray.origin.z) / ray.direction.z;
+            let point = ray.at(t);
+            if t < 0.0 || point.x * point.x + point.y * point.y > element.aperture_radius * element.aperture_radius {
+                return None;
+            }
+            return Some(Ray::new(point, ray.direction));
+        }
+
+        let (t, normal) = LensSystem::intersect_spherical(element.curvature_radius, z + element.curvature_radius, ray)?;
+        let point = ray.at(t);
+        if point.x * point.x + point.y * point.y > element.aperture_radius * element.aperture_radius {
+            return None;
+        }
+        let unit_direction = ray.direction.normalize();
+        let eta = eta_i / eta_t;
+        let cos_theta = unit_direction.neg().dot(&normal);
+        if eta * eta * (1.0 - cos_theta * cos_theta) > 1.0 {
+            return None;
+        }
+        Some(Ray::new(point, Vector3::refract(&unit_direction, &normal, eta)))
+    }
+
+    fn trace_from_film(&self, ray: &Ray) -> Option<Ray> {
+        let mut z = 0.0;
+        let mut ray = Ray::new(ray.origin, ray.direction);
+        for i in (0..self.elements.len()).rev() {
+            let element = &self.elements[i];
+            z -= element.thickness;
+            let eta_t = if i > 0 { self.elements[i - 1].refraction_index } else { 1.0 };
+            ray = LensSystem::cross(element, z, &ray, element.refraction_index, eta_t)?;
+        }
+        Some(ray)
+    }
+
+    fn trace_from_scene(&self, ray: &Ray) -> Option<Ray> {
+        let mut z = -self.front_z();
+        let mut ray = Ray::new(ray.origin, ray.direction);
+        for (i, element) in self.elements.iter().enumerate() {
+            let eta_i = if i > 0 { self.elements[i - 1].refraction_index } else { 1.0 };
+            ray = LensSystem::cross(element, z, &ray, eta_i, element.refraction_index)?;
+            z += element.thickness;
+        }
+        Some(ray)
+    }
+
+    /// Axial positions of the principal plane and focal point on the side a
+    /// paraxial ray at height `x` exits toward.
+    fn cardinal_points(x: f64, exiting: &Ray) -> (f64, f64) {
+        let o = exiting.origin;
+        let d = exiting.direction;
+        let focal_z = o.z - o.x / d.x * d.z;
+        let principal_z = o.z + (x - o.x) / d.x * d.z;
+        (principal_z, focal_z)
+    }
+
+    /// Focal length and the front and rear principal planes, relative to
+    /// the rear vertex, of the thick lens approximating the system. `None`
+    /// if paraxial rays are blocked.
+    fn thick_lens(&self) -> Option<(f64, f64, f64)> {
+        let x = 0.001 * self.film_diagonal;
+        let scene_ray = Ray::new(Point3::new(x, 0.0, -self.front_z() - 1.0), Vector3::new(0.0, 0.0, 1.0));
+        let film_ray = Ray::new(Point3::new(x, 0.0, 1.0), Vector3::new(0.0, 0.0, -1.0));
+        let rear = self.trace_from_scene(&scene_ray)?;
+        let front = self.trace_from_film(&film_ray)?;
+        let (rear_principal, rear_focal) = LensSystem::cardinal_points(x, &rear);
+        let (front_principal, _) = LensSystem::cardinal_points(x, &front);
+        let rear_vertex = -self.elements.last().unwrap().thickness;
+        Some((rear_focal - rear_principal, front_principal - rear_vertex, rear_principal - rear_vertex))
+    }
+
+    /// Move the film so that an object `distance` mm in front of it is in
+    /// focus, using the thick lens approximation of the system.
+    fn focus(&mut self, distance: f64) {
+        // The object and image distances, measured from their principal
+        // planes, add up to `total` wherever the film is.
+        let total = distance + self.front_principal - self.rear_principal;
+        if total < 4.0 * self.focal_length {
+            panic!("Focus distance is closer than the lens can focus");
+        }
+        let image_distance = 0.5 * (total - (total * (total - 4.0 * self.focal_length)).sqrt());
+        self.elements.last_mut().unwrap().thickness = self.rear_principal + image_distance;
+    }
+}
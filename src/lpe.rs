@@ -0,0 +1,154 @@
+use crate::vector3::Vector3;
+
+/// Kind of scattering event that generated a ray.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Lobe {
+    Diffuse,
+    /// Reflection off a smooth or glossy interface.
+    Specular,
+    /// Refraction through an interface.
+    Transmission,
+    /// Scattering inside a medium.
+    Volume,
+}
+
+impl Lobe {
+    /// Specular for a `direction` leaving on the side of `normal`,
+    /// transmission for one passing through the surface.
+    pub fn specular_or_transmission(direction: &Vector3, normal: &Vector3) -> Self {
+        if direction.dot(normal) > 0.0 {
+            Lobe::Specular
+        } else {
+            Lobe::Transmission
+        }
+    }
+
+    /// Event letter of the lobe in light path expressions.
+    pub fn event(&self) -> u8 {
+        match self {
+            Lobe::Diffuse => b'D',
+            Lobe::Specular => b'S',
+            Lobe::Transmission => b'T',
+            Lobe::Volume => b'V',
+        }
+    }
+}
+
+/// Letters of the events along a path: the camera, one scattering event
+/// per bounce, then an emitter or the background.
+const EVENTS: &[u8] = b"CDSTVLB";
+
+#[derive(Debug, Clone)]
+enum Atom {
+    Event(u8),
+    Any,
+    /// Set of events, matching those outside it when negated.
+    Class(Vec<u8>, bool),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Repeat {
+    Once,
+    Optional,
+    ZeroOrMore,
+    OneOrMore,
+}
+
+/// Regular expression over the events of a light path, naming a bucket
+/// that collects the light of every path it matches. Paths are written
+/// from the camera: `C`, then `D`iffuse, `S`pecular, `T`ransmission or
+/// `V`olume per bounce, ending in `L` for an emitter or `B` for the
+/// background. Expressions use event letters, `.` for any event, classes
+/// such as `[LB]` or `[^D]`, and the `?`, `*` and `+` repetitions, so
+/// `CD[LB]` is direct diffuse and `CD.+[LB]` indirect diffuse light.
+#[derive(Debug, Clone)]
+pub struct LightPathExpression {
+    pub name: String,
+    pub expression: String,
+    pieces: Vec<(Atom, Repeat)>,
+}
+
+impl LightPathExpression {
+    pub fn new(name: &str, expression: &str) -> Self {
+        let invalid = |message: &str| -> ! { panic!("Invalid light path expression {:?}: {}", expression, message) };
+        let mut pieces = Vec::new();
+        let mut chars = expression.bytes().filter(|c| !c.is_ascii_whitespace()).peekable();
+        while let Some(c) = chars.next() {
+            let atom = match c {
+                b'.' => Atom::Any,
+                b'[' => {
+                    let negated = chars.next_if_eq(&b'^').is_some();
+                    let mut events = Vec::new();
+                    loop {
+                        match chars.next() {
+                            Some(b']') => break,
+                            Some(event) if EVENTS.contains(&event) => events.push(event),
+                            Some(_) => invalid("unknown event in class"),
+                            None => invalid("unterminated class"),
+                        }
+                    }
+                    Atom::Class(events, negated)
+                }
+                event if EVENTS.contains(&event) => Atom::Event(event),
+                _ => invalid("unknown event"),
+            };
+            let repeat = match chars.next_if(|c| b"?*+".contains(c)) {
+                Some(b'?') => Repeat::Optional,
+                Some(b'*') => Repeat::ZeroOrMore,
+                Some(b'+') => Repeat::OneOrMore,
+                _ => Repeat::Once,
+            };
+            pieces.push((atom, repeat));
+        }
+        LightPathExpression {
+            name: name.to_string(),
+            expression: expression.to_string(),
+            pieces,
+        }
+    }
+
+    /// Buckets splitting the beauty into light seen directly and diffuse,
+    /// specular, transmission and volume light, each direct or indirect
+    /// by whether the path bounces again after its first event.
+    pub fn standard() -> Vec<Self> {
+        vec![
+            LightPathExpression::new("emission", "C[LB]"),
+            LightPathExpression::new("diffuse_direct", "CD[LB]"),
+            LightPathExpression::new("diffuse_indirect", "CD.+[LB]"),
+            LightPathExpression::new("specular_direct", "CS[LB]"),
+            LightPathExpression::new("specular_indirect", "CS.+[LB]"),
+            LightPathExpression::new("transmission_direct", "CT[LB]"),
+            LightPathExpression::new("transmission_indirect", "CT.+[LB]"),
+            LightPathExpression::new("volume_direct", "CV[LB]"),
+            LightPathExpression::new("volume_indirect", "CV.+[LB]"),
+        ]
+    }
+
+    /// Whether the whole of `path`, a string of event letters, matches.
+    pub fn matches(&self, path: &[u8]) -> bool {
+        match_pieces(&self.pieces, path)
+    }
+}
+
+fn match_atom(atom: &Atom, event: u8) -> bool {
+    match atom {
+        Atom::Event(expected) => *expected == event,
+        Atom::Any => true,
+        Atom::Class(events, negated) => events.contains(&event) != *negated,
+    }
+}
+
+/// Backtracking match, trying the longest repetition first.
+fn match_pieces(pieces: &[(Atom, Repeat)], path: &[u8]) -> bool {
+    let Some(((atom, repeat), rest)) = pieces.split_first() else {
+        return path.is_empty();
+    };
+    let (min, max) = match repeat {
+        Repeat::Once => (1, 1),
+        Repeat::Optional => (0, 1),
+        Repeat::ZeroOrMore => (0, path.len()),
+        Repeat::OneOrMore => (1, path.len()),
+    };
+    let longest = path.iter().take(max).take_while(|&&event| match_atom(atom, event)).count();
+    (min..=longest).rev().any(|count| match_pieces(rest, &path[count..]))
+}
@@ -16,6 +16,42 @@ mod material;
 mod lambertian;
 mod metal;
 mod dielectric;
+mod onb;
+mod microfacet;
+mod rough_dielectric;
+mod texture;
+mod principled;
+mod spectrum;
+mod ior;
+mod isotropic;
+mod henyey_greenstein;
+mod constant_medium;
+mod fog;
+mod aabb;
+mod voxel_grid;
+mod heterogeneous_medium;
+mod subsurface;
+mod thin_film;
+mod coated;
+mod normal_mapped;
+mod bump_mapped;
+mod alpha_masked;
+mod projection;
+mod stereo;
+mod lens_system;
+mod aperture;
+mod physical_lens;
+mod sampler;
+mod blue_noise;
+mod adaptive;
+mod filter;
+mod film;
+mod checkpoint;
+mod denoiser;
+mod exr;
+mod aov;
+mod lpe;
+mod tone_mapping;
 
 use camera::Camera;
 use hittables::Hittables;
@@ -33,6 +69,7 @@ const SAMPLES_PER_PIXEL: u32 = 20;
 const MAX_DEPTH: u32 = 50;
 const DEFOCUS_ANGLE: f64 = 0.6;
 const FOCUS_DISTANCE: f64 = 10.0;
+const SPECTRAL: bool = false;
 
 fn main() {
     let lookfrom = Point3::new(13.0, 2.0, 3.0);
@@ -49,7 +86,8 @@ fn main() {
         MAX_DEPTH,
         DEFOCUS_ANGLE,
         FOCUS_DISTANCE,
-    );
+    )
+    .with_spectral(SPECTRAL);
     let mut hittables = Hittables::new();
 
     let ground_material = Box::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
@@ -4,4 +4,15 @@ use crate::ray::Ray;
 
 pub trait Material {
     fn scatter(&self, ray_in: &Ray, hit_record: &HitRecord) -> Option<(Color, Ray)>;
+
+    /// Radiance emitted towards the incoming ray at the hit point.
+    fn emitted(&self, _hit_record: &HitRecord) -> Color {
+        Color::new(0.0, 0.0, 0.0)
+    }
+
+    /// Coverage of the surface at the hit point. Hits with an opacity below
+    /// 1.0 are skipped stochastically, so every ray sees through cutouts.
+    fn opacity(&self, _hit_record: &HitRecord) -> f64 {
+        1.0
+    }
 }
@@ -1,20 +1,46 @@
 use crate::color::Color;
 use crate::material::Material;
+use crate::lpe::Lobe;
 use crate::ray::Ray;
+use crate::thin_film::{Substrate, ThinFilm, RGB_WAVELENGTHS};
 use crate::vector3::Vector3;
 use crate::hittable::HitRecord;
 
 pub struct Metal {
     albedo: Color,
     fuzz: f64,
+    thin_film: Option<ThinFilm>,
 }
 
 impl Metal {
     pub fn new(albedo: Color, fuzz: f64) -> Self {
-        if fuzz < 0.0 || fuzz > 1.0 {
+        if !(0.0..=1.0).contains(&fuzz) {
             panic!("Fuzz must be between 0.0 and 1.0");
         }
-        Metal { albedo, fuzz }
+        Metal {
+            albedo,
+            fuzz,
+            thin_film: None,
+        }
+    }
+
+    /// Coat the metal with an interference film, e.g. heat tinted steel.
+    /// The albedo then sets the reflectivity of the metal under the film.
+    pub fn with_thin_film(mut self, thin_film: ThinFilm) -> Self {
+        self.thin_film = Some(thin_film);
+        self
+    }
+
+    /// Albedo at `lambda` nanometres, interpolated between the RGB channels.
+    fn reflectivity_at(&self, lambda: f64) -> f64 {
+        let [red, green, blue] = RGB_WAVELENGTHS;
+        if lambda >= green {
+            let t = ((lambda - green) / (red - green)).min(1.0);
+            self.albedo.g() * (1.0 - t) + self.albedo.r() * t
+        } else {
+            let t = ((green - lambda) / (green - blue)).min(1.0);
+            self.albedo.g() * (1.0 - t) + self.albedo.b() * t
+        }
     }
 }
 
@@ -22,11 +48,20 @@ impl Material for Metal {
     fn scatter(&self, ray_in: &Ray, hit_record: &HitRecord) -> Option<(Color, Ray)> {
         let mut reflected = Vector3::reflect(&ray_in.direction, &hit_record.normal);
         reflected = reflected.normalize().add(&Vector3::random_unit_vector().mul(self.fuzz));
-        let scattered = Ray::new(hit_record.point, reflected);
-        if scattered.direction.dot(&hit_record.normal) > 0.0 {
-            Some((self.albedo, scattered))
-        } else {
-            None
+        let scattered = Ray::new(hit_record.point, reflected).with_lobe(Lobe::Specular);
+        if scattered.direction.dot(&hit_record.normal) <= 0.0 {
+            return None;
+        }
+        match &self.thin_film {
+            Some(thin_film) => {
+                let cos_theta = ray_in.direction.normalize().neg().dot(&hit_record.normal);
+                let (reflectance, wavelengths) = thin_film.reflectance_for(ray_in, cos_theta, 1.0, |lambda| {
+                    let reflectivity = self.reflectivity_at(lambda);
+                    Substrate::from_reflectivity(reflectivity, reflectivity)
+                });
+                Some((reflectance, scattered.with_wavelengths(wavelengths)))
+            }
+            None => Some((self.albedo, scattered)),
         }
     }
 }
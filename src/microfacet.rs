@@ -0,0 +1,137 @@
+use std::f64::consts::PI;
+use crate::vector3::Vector3;
+
+/// Exact Fresnel reflectance for unpolarized light at a dielectric boundary.
+/// `cos_theta_i` is the cosine between the incident direction and the normal,
+/// `etai_over_etat` the ratio of refractive indices across the boundary.
+pub fn fresnel_dielectric(cos_theta_i: f64, etai_over_etat: f64) -> f64 {
+    let cos_theta_i = cos_theta_i.clamp(0.0, 1.0);
+    let sin2_theta_t = etai_over_etat * etai_over_etat * (1.0 - cos_theta_i * cos_theta_i);
+    if sin2_theta_t >= 1.0 {
+        return 1.0;
+    }
+    let cos_theta_t = (1.0 - sin2_theta_t).sqrt();
+    let r_s = (etai_over_etat * cos_theta_i - cos_theta_t) / (etai_over_etat * cos_theta_i + cos_theta_t);
+    let r_p = (cos_theta_i - etai_over_etat * cos_theta_t) / (cos_theta_i + etai_over_etat * cos_theta_t);
+    0.5 * (r_s * r_s + r_p * r_p)
+}
+
+/// Schlick's approximation of Fresnel reflectance given the reflectance at
+/// normal incidence.
+pub fn fresnel_schlick(f0: f64, cos_theta: f64) -> f64 {
+    f0 + (1.0 - f0) * (1.0 - cos_theta.clamp(0.0, 1.0)).powi(5)
+}
+
+/// Isotropic GGX (Trowbridge-Reitz) microfacet distribution.
+/// Directions are expressed in a local frame where the macro normal is +z.
+pub struct Ggx {
+    pub alpha: f64,
+}
+
+impl Ggx {
+    /// Create a distribution from a perceptual roughness in [0, 1].
+    pub fn from_roughness(roughness: f64) -> Self {
+        let roughness = roughness.clamp(0.0, 1.0);
+        Ggx {
+            alpha: (roughness * roughness).max(1e-4),
+        }
+    }
+
+    pub fn d(&self, m: &Vector3) -> f64 {
+        if m.z <= 0.0 {
+            return 0.0;
+        }
+        let a2 = self.alpha * self.alpha;
+        let cos2 = m.z * m.z;
+        let denom = cos2 * (a2 - 1.0) + 1.0;
+        a2 / (PI * denom * denom)
+    }
+
+    fn lambda(&self, w: &Vector3) -> f64 {
+        let cos2 = w.z * w.z;
+        if cos2 <= 0.0 {
+            return f64::INFINITY;
+        }
+        let tan2 = (1.0 - cos2).max(0.0) / cos2;
+        0.5 * (-1.0 + (1.0 + self.alpha * self.alpha * tan2).sqrt())
+    }
+
+    /// Smith masking term for a single direction.
+    pub fn g1(&self, w: &Vector3) -> f64 {
+        1.0 / (1.0 + self.lambda(w))
+    }
+
+    /// Height-correlated Smith masking-shadowing term.
+    pub fn g2(&self, wo: &Vector3, wi: &Vector3) -> f64 {
+        1.0 / (1.0 + self.lambda(wo) + self.lambda(wi))
+    }
+
+    /// Sample a microfacet normal from the distribution of normals visible
+    /// from `wo` (Heitz 2018). `wo` must lie in the upper hemisphere.
+    pub fn sample_visible_normal(&self, wo: &Vector3, u1: f64, u2: f64) -> Vector3 {
+        let vh = Vector3::new(self.alpha * wo.x, self.alpha * wo.y, wo.z).normalize();
+        let length_squared = vh.x * vh.x + vh.y * vh.y;
+        let t1 = if length_squared > 0.0 {
+            Vector3::new(-vh.y, vh.x, 0.0).div(length_squared.sqrt())
+        } else {
+            Vector3::new(1.0, 0.0, 0.0)
+        };
+        let t2 = vh.cross(&t1);
+        let r = u1.sqrt();
+        let phi = 2.0 * PI * u2;
+        let p1 = r * phi.cos();
+        let s = 0.5 * (1.0 + vh.z);
+        let p2 = (1.0 - s) * (1.0 - p1 * p1).sqrt() + s * r * phi.sin();
+        let nh = t1
+            .mul(p1)
+            .add(&t2.mul(p2))
+            .add(&vh.mul((1.0 - p1 * p1 - p2 * p2).max(0.0).sqrt()));
+        Vector3::new(self.alpha * nh.x, self.alpha * nh.y, nh.z.max(1e-6)).normalize()
+    }
+
+    /// Mirror `wo` about a sampled visible normal. Returns the incident
+    /// direction and the cosine between `wo` and the microfacet normal,
+    /// or `None` if the reflection points below the surface.
+    pub fn sample_reflection(&self, wo: &Vector3, u1: f64, u2: f64) -> Option<(Vector3, f64)> {
+        let m = self.sample_visible_normal(wo, u1, u2);
+        let wi = Vector3::reflect(&wo.neg(), &m);
+        if wi.z <= 0.0 {
+            return None;
+        }
+        Some((wi, wo.dot(&m)))
+    }
+
+    /// Scatter `wo` off a rough dielectric interface, choosing between
+    /// reflection and refraction with the exact Fresnel term of the sampled
+    /// microfacet. Returns the incident direction and whether it refracted.
+    pub fn sample_dielectric(
+        &self,
+        wo: &Vector3,
+        etai_over_etat: f64,
+        u1: f64,
+        u2: f64,
+        u3: f64,
+    ) -> Option<(Vector3, bool)> {
+        let m = self.sample_visible_normal(wo, u1, u2);
+        let fresnel = fresnel_dielectric(wo.dot(&m), etai_over_etat);
+        if u3 < fresnel {
+            let wi = Vector3::reflect(&wo.neg(), &m);
+            if wi.z <= 0.0 {
+                return None;
+            }
+            Some((wi, false))
+        } else {
+            let wi = Vector3::refract(&wo.neg(), &m, etai_over_etat);
+            if wi.z >= 0.0 {
+                return None;
+            }
+            Some((wi, true))
+        }
+    }
+
+    /// Path weight of a direction produced by visible normal sampling,
+    /// which reduces to the masking term of the sampled direction.
+    pub fn sample_weight(&self, wi: &Vector3) -> f64 {
+        self.g1(&Vector3::new(wi.x, wi.y, wi.z.abs()))
+    }
+}
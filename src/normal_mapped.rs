@@ -0,0 +1,55 @@
+use crate::color::Color;
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::texture::Texture;
+use crate::hittable::HitRecord;
+
+/// Perturbs the shading normal of a base material with a tangent space
+/// normal map, where red and green follow the surface's `u` and `v`.
+pub struct NormalMapped {
+    base: Box<dyn Material>,
+    normal_map: Box<dyn Texture>,
+    strength: f64,
+}
+
+impl NormalMapped {
+    pub fn new(base: Box<dyn Material>, normal_map: Box<dyn Texture>, strength: f64) -> Self {
+        NormalMapped {
+            base,
+            normal_map,
+            strength,
+        }
+    }
+
+    fn shade<'a>(&self, ray_in: &Ray, hit_record: &HitRecord<'a>) -> HitRecord<'a> {
+        let sample = self.normal_map.value(hit_record.u, hit_record.v, &hit_record.point);
+        let x = (2.0 * sample.r() - 1.0) * self.strength;
+        let y = (2.0 * sample.g() - 1.0) * self.strength;
+        let z = 2.0 * sample.b() - 1.0;
+
+        let normal = hit_record.outward_normal();
+        let tangent = hit_record.tangent.sub(&normal.mul(normal.dot(&hit_record.tangent))).normalize();
+        let bitangent = normal.cross(&tangent);
+        let perturbed = tangent.mul(x).add(&bitangent.mul(y)).add(&normal.mul(z)).normalize();
+        hit_record.with_shading_normal(perturbed, &ray_in.direction)
+    }
+}
+
+impl Material for NormalMapped {
+    fn scatter(&self, ray_in: &Ray, hit_record: &HitRecord) -> Option<(Color, Ray)> {
+        let shaded = self.shade(ray_in, hit_record);
+        let (attenuation, scattered) = self.base.scatter(ray_in, &shaded)?;
+        if !shaded.is_consistent(&scattered.direction) {
+            return None;
+        }
+        Some((attenuation, scattered))
+    }
+
+    fn emitted(&self, hit_record: &HitRecord) -> Color {
+        self.base.emitted(hit_record)
+    }
+
+    fn opacity(&self, hit_record: &HitRecord) -> f64 {
+        self.base.opacity(hit_record)
+    }
+}
@@ -0,0 +1,34 @@
+use crate::vector3::Vector3;
+
+/// Orthonormal basis built around a normal, used to move directions
+/// between world space and a local shading frame where the normal is +z.
+pub struct Onb {
+    pub u: Vector3,
+    pub v: Vector3,
+    pub w: Vector3,
+}
+
+impl Onb {
+    pub fn new(normal: &Vector3) -> Self {
+        let w = normal.normalize();
+        let a = if w.x.abs() > 0.9 {
+            Vector3::new(0.0, 1.0, 0.0)
+        } else {
+            Vector3::new(1.0, 0.0, 0.0)
+        };
+        let v = w.cross(&a).normalize();
+        let u = w.cross(&v);
+        Onb { u, v, w }
+    }
+
+    pub fn to_world(&self, local: &Vector3) -> Vector3 {
+        self.u
+            .mul(local.x)
+            .add(&self.v.mul(local.y))
+            .add(&self.w.mul(local.z))
+    }
+
+    pub fn to_local(&self, world: &Vector3) -> Vector3 {
+        Vector3::new(world.dot(&self.u), world.dot(&self.v), world.dot(&self.w))
+    }
+}
@@ -0,0 +1,35 @@
+/// Photographic description of a thin lens, converted into the camera's
+/// `vfov` and `defocus_angle`. Scene units are taken to be metres.
+#[derive(Debug, Clone, Copy)]
+pub struct PhysicalLens {
+    /// Focal length in mm.
+    pub focal_length: f64,
+    /// Sensor width in mm, e.g. 36 for full frame.
+    pub sensor_width: f64,
+    pub f_stop: f64,
+}
+
+impl PhysicalLens {
+    pub fn new(focal_length: f64, sensor_width: f64, f_stop: f64) -> Self {
+        if focal_length <= 0.0 || sensor_width <= 0.0 {
+            panic!("Focal length and sensor width must be positive");
+        }
+        if f_stop <= 0.0 {
+            panic!("F-stop must be positive");
+        }
+        PhysicalLens { focal_length, sensor_width, f_stop }
+    }
+
+    /// Vertical field of view in degrees for a sensor cropped to `aspect_ratio`.
+    pub fn vfov(&self, aspect_ratio: f64) -> f64 {
+        let sensor_height = self.sensor_width / aspect_ratio;
+        2.0 * (sensor_height / (2.0 * self.focal_length)).atan().to_degrees()
+    }
+
+    /// Cone angle in degrees subtended by the entrance pupil at the plane in
+    /// focus, `focus_distance` metres away.
+    pub fn defocus_angle(&self, focus_distance: f64) -> f64 {
+        let pupil_radius = 0.5 * self.focal_length / self.f_stop * 0.001;
+        2.0 * (pupil_radius / focus_distance).atan().to_degrees()
+    }
+}
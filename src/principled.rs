@@ -0,0 +1,179 @@
+use crate::color::Color;
+use crate::material::Material;
+use crate::microfacet::{fresnel_dielectric, fresnel_schlick, Ggx};
+use crate::onb::Onb;
+use crate::lpe::Lobe;
+use crate::ray::Ray;
+use crate::texture::{SolidColor, Texture};
+use crate::utils::lerp;
+use crate::vector3::Vector3;
+use crate::hittable::HitRecord;
+use crate::sampler;
+
+const CLEARCOAT_ROUGHNESS: f64 = 0.1;
+const CLEARCOAT_REFRACTION_INDEX: f64 = 1.5;
+
+/// Disney-style principled material. Each lobe is picked stochastically in
+/// proportion to its layer weight, so a single `scatter` call samples one of
+/// clearcoat, metal, glass, dielectric specular or diffuse (with sheen and
+/// subsurface flattening folded into the diffuse lobe).
+pub struct Principled {
+    pub base_color: Box<dyn Texture>,
+    pub metallic: Box<dyn Texture>,
+    pub roughness: Box<dyn Texture>,
+    pub specular: Box<dyn Texture>,
+    pub transmission: Box<dyn Texture>,
+    pub clearcoat: Box<dyn Texture>,
+    pub sheen: Box<dyn Texture>,
+    pub subsurface: Box<dyn Texture>,
+    pub refraction_index: f64,
+}
+
+impl Principled {
+    /// A rough white dielectric; override parameters with the `with_*` methods.
+    pub fn new(base_color: Box<dyn Texture>) -> Self {
+        Principled {
+            base_color,
+            metallic: Box::new(SolidColor::scalar(0.0)),
+            roughness: Box::new(SolidColor::scalar(0.5)),
+            specular: Box::new(SolidColor::scalar(0.5)),
+            transmission: Box::new(SolidColor::scalar(0.0)),
+            clearcoat: Box::new(SolidColor::scalar(0.0)),
+            sheen: Box::new(SolidColor::scalar(0.0)),
+            subsurface: Box::new(SolidColor::scalar(0.0)),
+            refraction_index: 1.5,
+        }
+    }
+
+    pub fn with_metallic(mut self, metallic: Box<dyn Texture>) -> Self {
+        self.metallic = metallic;
+        self
+    }
+
+    pub fn with_roughness(mut self, roughness: Box<dyn Texture>) -> Self {
+        self.roughness = roughness;
+        self
+    }
+
+    pub fn with_specular(mut self, specular: Box<dyn Texture>) -> Self {
+        self.specular = specular;
+        self
+    }
+
+    pub fn with_transmission(mut self, transmission: Box<dyn Texture>) -> Self {
+        self.transmission = transmission;
+        self
+    }
+
+    pub fn with_clearcoat(mut self, clearcoat: Box<dyn Texture>) -> Self {
+        self.clearcoat = clearcoat;
+        self
+    }
+
+    pub fn with_sheen(mut self, sheen: Box<dyn Texture>) -> Self {
+        self.sheen = sheen;
+        self
+    }
+
+    pub fn with_subsurface(mut self, subsurface: Box<dyn Texture>) -> Self {
+        self.subsurface = subsurface;
+        self
+    }
+
+    pub fn with_refraction_index(mut self, refraction_index: f64) -> Self {
+        self.refraction_index = refraction_index;
+        self
+    }
+
+    fn scalar(texture: &dyn Texture, hit_record: &HitRecord) -> f64 {
+        texture.value(hit_record.u, hit_record.v, &hit_record.point).r()
+    }
+
+    /// Burley diffuse blended with the Hanrahan-Krueger inspired subsurface
+    /// approximation, plus the sheen retro-reflection, as a path weight for a
+    /// cosine-weighted direction.
+    fn diffuse_weight(
+        base_color: &Color,
+        roughness: f64,
+        sheen: f64,
+        subsurface: f64,
+        wo: &Vector3,
+        wi: &Vector3,
+    ) -> Color {
+        let h = wo.add(wi).normalize();
+        let cos_d = wi.dot(&h);
+        let fl = (1.0 - wi.z).powi(5);
+        let fv = (1.0 - wo.z).powi(5);
+
+        let fd90 = 0.5 + 2.0 * roughness * cos_d * cos_d;
+        let fd = (1.0 + (fd90 - 1.0) * fl) * (1.0 + (fd90 - 1.0) * fv);
+
+        let fss90 = roughness * cos_d * cos_d;
+        let fss = (1.0 + (fss90 - 1.0) * fl) * (1.0 + (fss90 - 1.0) * fv);
+        let ss = 1.25 * (fss * (1.0 / (wi.z + wo.z) - 0.5) + 0.5);
+
+        let retro = fd * (1.0 - subsurface) + ss * subsurface;
+        let sheen_weight = sheen * (1.0 - cos_d).powi(5) * std::f64::consts::PI;
+        base_color.mul(retro).add(&Color::new(1.0, 1.0, 1.0).mul(sheen_weight))
+    }
+}
+
+impl Material for Principled {
+    fn scatter(&self, ray_in: &Ray, hit_record: &HitRecord) -> Option<(Color, Ray)> {
+        let (u, v, point) = (hit_record.u, hit_record.v, &hit_record.point);
+        let base_color = self.base_color.value(u, v, point);
+        let metallic = Principled::scalar(&*self.metallic, hit_record);
+        let roughness = Principled::scalar(&*self.roughness, hit_record);
+        let specular = Principled::scalar(&*self.specular, hit_record);
+        let transmission = Principled::scalar(&*self.transmission, hit_record);
+        let clearcoat = Principled::scalar(&*self.clearcoat, hit_record);
+        let sheen = Principled::scalar(&*self.sheen, hit_record);
+        let subsurface = Principled::scalar(&*self.subsurface, hit_record);
+
+        let onb = Onb::new(&hit_record.normal);
+        let wo = onb.to_local(&ray_in.direction.normalize().neg());
+        if wo.z <= 0.0 {
+            return None;
+        }
+
+        let ggx = Ggx::from_roughness(roughness);
+        let white = Color::new(1.0, 1.0, 1.0);
+
+        // Only the glass lobe lets rays inside, so a back face hit is a ray
+        // leaving through the transmissive interface.
+        let inside = !hit_record.front_face;
+
+        let (attenuation, wi, lobe) = if !inside && sampler::get_1d() < clearcoat * fresnel_dielectric(wo.z, 1.0 / CLEARCOAT_REFRACTION_INDEX) {
+            let coat = Ggx::from_roughness(CLEARCOAT_ROUGHNESS);
+            let (u1, u2) = sampler::get_2d();
+            let (wi, _) = coat.sample_reflection(&wo, u1, u2)?;
+            (white.mul(coat.sample_weight(&wi)), wi, Lobe::Specular)
+        } else if !inside && sampler::get_1d() < metallic {
+            let (u1, u2) = sampler::get_2d();
+            let (wi, cos_m) = ggx.sample_reflection(&wo, u1, u2)?;
+            let fresnel = lerp(base_color, white, fresnel_schlick(0.0, cos_m));
+            (fresnel.mul(ggx.sample_weight(&wi)), wi, Lobe::Specular)
+        } else if inside || sampler::get_1d() < transmission {
+            let refraction_ratio = if inside {
+                self.refraction_index
+            } else {
+                1.0 / self.refraction_index
+            };
+            let (u1, u2) = sampler::get_2d();
+            let (wi, refracted) = ggx.sample_dielectric(&wo, refraction_ratio, u1, u2, sampler::get_1d())?;
+            let tint = if refracted && !inside { base_color } else { white };
+            let lobe = if refracted { Lobe::Transmission } else { Lobe::Specular };
+            (tint.mul(ggx.sample_weight(&wi)), wi, lobe)
+        } else if sampler::get_1d() < fresnel_schlick(0.08 * specular, wo.z) {
+            let (u1, u2) = sampler::get_2d();
+            let (wi, _) = ggx.sample_reflection(&wo, u1, u2)?;
+            (white.mul(ggx.sample_weight(&wi)), wi, Lobe::Specular)
+        } else {
+            let wi = Vector3::random_cosine_direction();
+            (Principled::diffuse_weight(&base_color, roughness, sheen, subsurface, &wo, &wi), wi, Lobe::Diffuse)
+        };
+
+        let scattered = Ray::new(hit_record.point, onb.to_world(&wi)).with_lobe(lobe);
+        Some((attenuation, scattered))
+    }
+}
@@ -0,0 +1,89 @@
+use std::f64::consts::PI;
+use crate::vector3::Vector3;
+
+/// Radial mapping of a fisheye lens.
+#[derive(Debug, Clone, Copy)]
+pub enum FisheyeModel {
+    /// Image radius proportional to the angle from the optical axis.
+    Equidistant,
+    /// Equal-area mapping, radius proportional to `sin(θ / 2)`.
+    Equisolid,
+}
+
+/// How the camera maps pixels to rays.
+#[derive(Debug, Clone, Copy)]
+pub enum Projection {
+    /// Pinhole (or thin lens) perspective with the camera's `vfov`.
+    Perspective,
+    /// Parallel rays through a viewport `viewport_height` world units tall.
+    Orthographic { viewport_height: f64 },
+    /// Full sphere latitude-longitude panorama, best with a 2:1 image.
+    Equirectangular,
+    /// Circular fisheye inscribed in the image, covering `fov` degrees.
+    Fisheye { model: FisheyeModel, fov: f64 },
+    /// Six 90° faces in a 3x2 grid, best with a 3:2 image. The top row holds
+    /// the right, left and up faces, the bottom row down, back and front.
+    CubeMap,
+}
+
+impl Projection {
+    /// Direction of the ray through image position `(x, y)` in pixels, in
+    /// camera space: x right, y up, z forward. Only the panoramic
+    /// projections are mapped here; positions outside a fisheye circle have
+    /// no ray.
+    pub fn direction(&self, x: f64, y: f64, width: f64, height: f64) -> Option<Vector3> {
+        match *self {
+            Projection::Perspective | Projection::Orthographic { .. } => None,
+            Projection::Equirectangular => {
+                let phi = (x / width - 0.5) * 2.0 * PI;
+                let theta = (0.5 - y / height) * PI;
+                Some(Vector3::new(
+                    theta.cos() * phi.sin(),
+                    theta.sin(),
+                    theta.cos() * phi.cos(),
+                ))
+            }
+            Projection::Fisheye { model, fov } => {
+                let radius = 0.5 * width.min(height);
+                let px = (x - 0.5 * width) / radius;
+                let py = (0.5 * height - y) / radius;
+                let r = (px * px + py * py).sqrt();
+                if r > 1.0 {
+                    return None;
+                }
+                let half_fov = 0.5 * fov.to_radians();
+                let theta = match model {
+                    FisheyeModel::Equidistant => r * half_fov,
+                    FisheyeModel::Equisolid => 2.0 * (r * (0.5 * half_fov).sin()).clamp(-1.0, 1.0).asin(),
+                };
+                let psi = py.atan2(px);
+                Some(Vector3::new(
+                    theta.sin() * psi.cos(),
+                    theta.sin() * psi.sin(),
+                    theta.cos(),
+                ))
+            }
+            Projection::CubeMap => {
+                let face_width = width / 3.0;
+                let face_height = height / 2.0;
+                let column = ((x / face_width) as usize).min(2);
+                let row = ((y / face_height) as usize).min(1);
+                let a = 2.0 * (x / face_width - column as f64) - 1.0;
+                let b = 1.0 - 2.0 * (y / face_height - row as f64);
+                let right = Vector3::new(1.0, 0.0, 0.0);
+                let up = Vector3::new(0.0, 1.0, 0.0);
+                let forward = Vector3::new(0.0, 0.0, 1.0);
+                // (face direction, face right, face up)
+                let (f, r, u) = match (row, column) {
+                    (0, 0) => (right, forward.neg(), up),
+                    (0, 1) => (right.neg(), forward, up),
+                    (0, _) => (up, right, forward.neg()),
+                    (_, 0) => (up.neg(), right, forward),
+                    (_, 1) => (forward.neg(), right.neg(), up),
+                    (_, _) => (forward, right, up),
+                };
+                Some(f.add(&r.mul(a)).add(&u.mul(b)))
+            }
+        }
+    }
+}
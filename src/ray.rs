@@ -1,13 +1,35 @@
+use crate::lpe::Lobe;
+use crate::spectrum::SampledWavelengths;
 use crate::vector3::{Point3, Vector3};
 
 pub struct Ray {
     pub origin: Point3,
     pub direction: Vector3,
+    /// Wavelengths carried by the path in spectral mode.
+    pub wavelengths: Option<SampledWavelengths>,
+    /// Lobe of the scattering event that produced the ray, `None` for
+    /// camera rays and materials that do not tell.
+    pub lobe: Option<Lobe>,
 }
 
 impl Ray {
     pub fn new(origin: Point3, direction: Vector3) -> Self {
-        Self { origin, direction }
+        Self {
+            origin,
+            direction,
+            wavelengths: None,
+            lobe: None,
+        }
+    }
+
+    pub fn with_wavelengths(mut self, wavelengths: Option<SampledWavelengths>) -> Self {
+        self.wavelengths = wavelengths;
+        self
+    }
+
+    pub fn with_lobe(mut self, lobe: Lobe) -> Self {
+        self.lobe = Some(lobe);
+        self
     }
 
     pub fn at(&self, t: f64) -> Point3 {
@@ -0,0 +1,99 @@
+use crate::color::Color;
+use crate::ior::Ior;
+use crate::material::Material;
+use crate::microfacet::Ggx;
+use crate::onb::Onb;
+use crate::lpe::Lobe;
+use crate::ray::Ray;
+use crate::spectrum::SampledWavelengths;
+use crate::utils::{absorption_from_tint, beer_lambert};
+use crate::vector3::Vector3;
+use crate::hittable::HitRecord;
+use crate::sampler;
+
+/// Dielectric with a GGX microfacet surface, for frosted glass and
+/// rough liquids. Reflection and transmission are chosen with the exact
+/// Fresnel term of the sampled microfacet.
+pub struct RoughDielectric {
+    pub refraction_index: f64,
+    pub roughness: f64,
+    pub absorption: Vector3,
+    /// Wavelength dependent index used instead of `refraction_index` in
+    /// spectral mode.
+    pub dispersion: Option<Ior>,
+}
+
+impl RoughDielectric {
+    pub fn new(refraction_index: f64, roughness: f64) -> Self {
+        if !(0.0..=1.0).contains(&roughness) {
+            panic!("Roughness must be between 0.0 and 1.0");
+        }
+        Self {
+            refraction_index,
+            roughness,
+            absorption: Vector3::default(),
+            dispersion: None,
+        }
+    }
+
+    /// Tint the interior so that light is filtered to `color` after
+    /// travelling `distance` units through the medium.
+    pub fn with_absorption(mut self, color: Color, distance: f64) -> Self {
+        if !(distance > 0.0 && distance.is_finite()) {
+            panic!("Absorption distance must be positive and finite");
+        }
+        if ![color.r(), color.g(), color.b()].iter().all(|x| x.is_finite()) {
+            panic!("Absorption tint must be finite");
+        }
+        self.absorption = absorption_from_tint(color, distance);
+        self
+    }
+
+    pub fn with_dispersion(mut self, dispersion: Ior) -> Self {
+        self.dispersion = Some(dispersion);
+        self
+    }
+
+    /// Index of refraction for the ray, and the wavelengths its scattered
+    /// ray should carry. A dispersive interface splits the wavelengths, so
+    /// only the hero wavelength survives.
+    fn refraction_index_for(&self, ray_in: &Ray) -> (f64, Option<SampledWavelengths>) {
+        match (&self.dispersion, ray_in.wavelengths) {
+            (Some(dispersion), Some(wavelengths)) => {
+                (dispersion.at(wavelengths.hero()), Some(wavelengths.terminate_secondary()))
+            }
+            _ => (self.refraction_index, ray_in.wavelengths),
+        }
+    }
+}
+
+impl Material for RoughDielectric {
+    fn scatter(&self, ray_in: &Ray, hit_record: &HitRecord) -> Option<(Color, Ray)> {
+        let attenuation = if hit_record.front_face {
+            Color::new(1.0, 1.0, 1.0)
+        } else {
+            beer_lambert(&self.absorption, hit_record.t * ray_in.direction.length())
+        };
+        let (refraction_index, wavelengths) = self.refraction_index_for(ray_in);
+        let refraction_ratio = if hit_record.front_face {
+            1.0 / refraction_index
+        } else {
+            refraction_index
+        };
+
+        let onb = Onb::new(&hit_record.normal);
+        let wo = onb.to_local(&ray_in.direction.normalize().neg());
+        if wo.z <= 0.0 {
+            return None;
+        }
+
+        let ggx = Ggx::from_roughness(self.roughness);
+        let (u1, u2) = sampler::get_2d();
+        let (wi, _) = ggx.sample_dielectric(&wo, refraction_ratio, u1, u2, sampler::get_1d())?;
+        let weight = ggx.sample_weight(&wi);
+        let direction = onb.to_world(&wi);
+        let lobe = Lobe::specular_or_transmission(&direction, &hit_record.normal);
+        let scattered = Ray::new(hit_record.point, direction).with_wavelengths(wavelengths).with_lobe(lobe);
+        Some((attenuation.mul(weight), scattered))
+    }
+}
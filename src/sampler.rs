@@ -0,0 +1,300 @@
+use std::cell::RefCell;
+use std::sync::OnceLock;
+use crate::blue_noise;
+
+/// Dimensions used by the camera: pixel offset, lens position and
+/// wavelength.
+pub const CAMERA_DIMENSIONS: u32 = 6;
+/// Dimensions reserved for each bounce. Anything a bounce draws past its
+/// budget (random walks, delta tracking) falls back to independent numbers.
+pub const BOUNCE_DIMENSIONS: u32 = 8;
+
+/// How the random numbers of each pixel sample are generated.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SamplerType {
+    /// Independent uniform random numbers.
+    Independent,
+    /// Jittered strata, shuffled independently for every dimension.
+    Stratified,
+    /// Halton sequence with Owen-scrambled digits per pixel.
+    Halton,
+    /// Owen-scrambled Sobol points, padded pairwise across dimensions.
+    Sobol,
+    /// The same Sobol points in every pixel, shifted by a blue-noise tile
+    /// so that neighbouring pixels err in opposite directions and the
+    /// noise looks like fine grain rather than clumps.
+    BlueNoise,
+}
+
+/// State of the sampler for the pixel sample being traced. Independent
+/// numbers are hashed from the seed, pixel, sample index and a counter, so
+/// a render is reproduced exactly by repeating its seed.
+struct Sampler {
+    sampler_type: SamplerType,
+    samples_per_pixel: u32,
+    pixel: (u32, u32),
+    pixel_seed: u64,
+    sample_index: u32,
+    dimension: u32,
+    dimension_limit: u32,
+    independent_seed: u64,
+    independent_count: u32,
+}
+
+thread_local! {
+    static SAMPLER: RefCell<Sampler> = const {
+        RefCell::new(Sampler {
+            sampler_type: SamplerType::Independent,
+            samples_per_pixel: 1,
+            pixel: (0, 0),
+            pixel_seed: 0,
+            sample_index: 0,
+            dimension: 0,
+            dimension_limit: CAMERA_DIMENSIONS,
+            independent_seed: 0,
+            independent_count: 0,
+        })
+    };
+}
+
+/// Start sample `sample_index` of pixel `(i, j)` on this thread, at the
+/// first camera dimension.
+pub fn start_pixel_sample(sampler_type: SamplerType, samples_per_pixel: u32, seed: u64, i: u32, j: u32, sample_index: u32) {
+    SAMPLER.with_borrow_mut(|sampler| {
+        sampler.sampler_type = sampler_type;
+        sampler.samples_per_pixel = samples_per_pixel.max(1);
+        sampler.pixel = (i, j);
+        sampler.pixel_seed = hash(&[seed, i as u64, j as u64]);
+        sampler.independent_seed = hash(&[sampler.pixel_seed, sample_index as u64]);
+        sampler.independent_count = 0;
+        sampler.sample_index = sample_index;
+        sampler.dimension = 0;
+        sampler.dimension_limit = CAMERA_DIMENSIONS;
+    });
+}
+
+/// Move to the dimensions reserved for bounce `bounce` (0 for the camera
+/// ray's first hit), so every path consumes the same dimensions there.
+pub fn start_bounce(bounce: u32) {
+    SAMPLER.with_borrow_mut(|sampler| {
+        sampler.dimension = CAMERA_DIMENSIONS + bounce * BOUNCE_DIMENSIONS;
+        sampler.dimension_limit = sampler.dimension + BOUNCE_DIMENSIONS;
+    });
+}
+
+pub fn get_1d() -> f64 {
+    SAMPLER.with_borrow_mut(|sampler| sampler.get_1d())
+}
+
+pub fn get_2d() -> (f64, f64) {
+    SAMPLER.with_borrow_mut(|sampler| sampler.get_2d())
+}
+
+impl Sampler {
+    fn get_1d(&mut self) -> f64 {
+        if self.sampler_type == SamplerType::Independent || self.dimension >= self.dimension_limit {
+            return self.independent();
+        }
+        let dimension = self.dimension;
+        self.dimension += 1;
+        let seed = hash(&[self.pixel_seed, dimension as u64]);
+        match self.sampler_type {
+            // Past the planned sample count, as in time-limited renders, the
+            // strata are used up and the shuffled sequences would repeat.
+            SamplerType::Stratified | SamplerType::Sobol if self.sample_index >= self.samples_per_pixel => {
+                random_float(seed, self.sample_index)
+            }
+            SamplerType::Stratified => {
+                let stratum = permutation_element(self.sample_index, self.samples_per_pixel, seed);
+                (stratum as f64 + random_float(seed, self.sample_index)) / self.samples_per_pixel as f64
+            }
+            SamplerType::Halton => owen_scrambled_radical_inverse(prime(dimension), self.sample_index as u64, seed),
+            SamplerType::BlueNoise => {
+                let base = sobol_sample(self.sample_index, 0, hash(&[dimension as u64]) as u32);
+                (base + self.blue_noise_shift(dimension)).fract()
+            }
+            _ => {
+                let index = permutation_element(self.sample_index, self.samples_per_pixel, seed);
+                sobol_sample(index, 0, mix_bits(seed) as u32)
+            }
+        }
+    }
+
+    fn get_2d(&mut self) -> (f64, f64) {
+        if self.sampler_type == SamplerType::Independent || self.dimension + 1 >= self.dimension_limit {
+            return (self.independent(), self.independent());
+        }
+        let dimension = self.dimension;
+        self.dimension += 2;
+        let seed = hash(&[self.pixel_seed, dimension as u64]);
+        match self.sampler_type {
+            SamplerType::Stratified | SamplerType::Sobol if self.sample_index >= self.samples_per_pixel => {
+                (random_float(seed, 2 * self.sample_index), random_float(seed, 2 * self.sample_index + 1))
+            }
+            SamplerType::Stratified => {
+                let columns = (self.samples_per_pixel as f64).sqrt().ceil() as u32;
+                let rows = self.samples_per_pixel.div_ceil(columns);
+                let stratum = permutation_element(self.sample_index, columns * rows, seed);
+                (
+                    ((stratum % columns) as f64 + random_float(seed, 2 * self.sample_index)) / columns as f64,
+                    ((stratum / columns) as f64 + random_float(seed, 2 * self.sample_index + 1)) / rows as f64,
+                )
+            }
+            SamplerType::Halton => {
+                let index = self.sample_index as u64;
+                (
+                    owen_scrambled_radical_inverse(prime(dimension), index, seed),
+                    owen_scrambled_radical_inverse(prime(dimension + 1), index, mix_bits(seed)),
+                )
+            }
+            SamplerType::BlueNoise => {
+                let seed = hash(&[dimension as u64]);
+                (
+                    (sobol_sample(self.sample_index, 0, seed as u32) + self.blue_noise_shift(dimension)).fract(),
+                    (sobol_sample(self.sample_index, 1, (seed >> 32) as u32) + self.blue_noise_shift(dimension + 1)).fract(),
+                )
+            }
+            _ => {
+                let index = permutation_element(self.sample_index, self.samples_per_pixel, seed);
+                (
+                    sobol_sample(index, 0, mix_bits(seed) as u32),
+                    sobol_sample(index, 1, mix_bits(seed ^ 0x9e37_79b9_7f4a_7c15) as u32),
+                )
+            }
+        }
+    }
+
+    fn independent(&mut self) -> f64 {
+        self.independent_count += 1;
+        random_float(self.independent_seed, self.independent_count)
+    }
+
+    /// Toroidal shift of this pixel's points in `dimension`, read from the
+    /// blue-noise tile at an offset that differs between dimensions.
+    fn blue_noise_shift(&self, dimension: u32) -> f64 {
+        let offset = hash(&[dimension as u64, 0xb105_e000]);
+        blue_noise::value(self.pixel.0.wrapping_add(offset as u32), self.pixel.1.wrapping_add((offset >> 32) as u32))
+    }
+}
+
+fn mix_bits(mut v: u64) -> u64 {
+    v ^= v >> 31;
+    v = v.wrapping_mul(0x7fb5_d329_728e_a185);
+    v ^= v >> 27;
+    v = v.wrapping_mul(0x81da_def4_bc2d_d44d);
+    v ^= v >> 33;
+    v
+}
+
+fn hash(values: &[u64]) -> u64 {
+    values
+        .iter()
+        .fold(0x243f_6a88_85a3_08d3, |h, &value| mix_bits(h ^ value.wrapping_add(0x9e37_79b9_7f4a_7c15)))
+}
+
+/// Uniform number in [0, 1) determined by `seed` and `index`.
+fn random_float(seed: u64, index: u32) -> f64 {
+    (hash(&[seed, index as u64]) >> 11) as f64 / (1u64 << 53) as f64
+}
+
+/// Element `i` of a pseudorandom permutation of `0..n` (Kensler, 2013).
+fn permutation_element(mut i: u32, n: u32, seed: u64) -> u32 {
+    let p = seed as u32;
+    let mut w = n.wrapping_sub(1);
+    w |= w >> 1;
+    w |= w >> 2;
+    w |= w >> 4;
+    w |= w >> 8;
+    w |= w >> 16;
+    loop {
+        i ^= p;
+        i = i.wrapping_mul(0xe170_893d);
+        i ^= p >> 16;
+        i ^= (i & w) >> 4;
+        i ^= p >> 8;
+        i = i.wrapping_mul(0x0929_eb3f);
+        i ^= p >> 23;
+        i ^= (i & w) >> 1;
+        i = i.wrapping_mul(1 | p >> 27);
+        i = i.wrapping_mul(0x6935_fa69);
+        i ^= (i & w) >> 11;
+        i = i.wrapping_mul(0x74dc_b303);
+        i ^= (i & w) >> 2;
+        i = i.wrapping_mul(0x9e50_1cc3);
+        i ^= (i & w) >> 2;
+        i = i.wrapping_mul(0xc860_a3df);
+        i &= w;
+        i ^= i >> 5;
+        if i < n {
+            return i.wrapping_add(p) % n;
+        }
+    }
+}
+
+/// Radical inverse of `index` in `base`, with every digit permuted by a
+/// hash of the digits before it.
+fn owen_scrambled_radical_inverse(base: u32, mut index: u64, seed: u64) -> f64 {
+    let base64 = base as u64;
+    let inverse_base = 1.0 / base as f64;
+    let mut inverse_base_power = 1.0;
+    let mut reversed_digits = 0u64;
+    let mut result = 0.0;
+    // Continue past the last nonzero digit of the index, so that the
+    // scrambled trailing zeros fill in the low-order bits as well.
+    while 1.0 - (base as f64 - 1.0) * inverse_base_power < 1.0 {
+        let digit = index % base64;
+        index /= base64;
+        let digit_seed = mix_bits(seed ^ reversed_digits);
+        let permuted = permutation_element(digit as u32, base, digit_seed);
+        inverse_base_power *= inverse_base;
+        result += permuted as f64 * inverse_base_power;
+        reversed_digits = reversed_digits.wrapping_mul(base64).wrapping_add(digit);
+    }
+    result.min(1.0 - f64::EPSILON)
+}
+
+/// First two dimensions of the Sobol sequence, Owen scrambled in base 2.
+fn sobol_sample(index: u32, dimension: u32, seed: u32) -> f64 {
+    let mut v = match dimension {
+        0 => index.reverse_bits(),
+        _ => {
+            let mut bits = 0u32;
+            let mut direction = 1u32 << 31;
+            let mut i = index;
+            while i != 0 {
+                if i & 1 != 0 {
+                    bits ^= direction;
+                }
+                i >>= 1;
+                direction ^= direction >> 1;
+            }
+            bits
+        }
+    };
+    // Fast nested uniform scramble (Laine and Karras, 2011).
+    v = v.reverse_bits();
+    v ^= v.wrapping_mul(0x3d20_adea);
+    v = v.wrapping_add(seed);
+    v = v.wrapping_mul((seed >> 16) | 1);
+    v ^= v.wrapping_mul(0x0552_6c56);
+    v ^= v.wrapping_mul(0x53a2_2864);
+    v = v.reverse_bits();
+    (v as f64 / 4_294_967_296.0).min(1.0 - f64::EPSILON)
+}
+
+/// The `n`th prime, one Halton base per dimension.
+fn prime(n: u32) -> u32 {
+    static PRIMES: OnceLock<Vec<u32>> = OnceLock::new();
+    let primes = PRIMES.get_or_init(|| {
+        let mut primes: Vec<u32> = Vec::new();
+        let mut candidate = 2;
+        while primes.len() < 1024 {
+            if primes.iter().take_while(|&&p| p * p <= candidate).all(|&p| candidate % p != 0) {
+                primes.push(candidate);
+            }
+            candidate += 1;
+        }
+        primes
+    });
+    primes[n as usize % primes.len()]
+}
@@ -0,0 +1,248 @@
+use std::sync::OnceLock;
+use crate::color::Color;
+use crate::vector3::Vector3;
+
+pub const LAMBDA_MIN: f64 = 360.0;
+pub const LAMBDA_MAX: f64 = 830.0;
+/// Number of wavelengths carried by each path.
+pub const N_WAVELENGTHS: usize = 4;
+
+/// Wavelengths (in nanometres) traced together along one path. The first
+/// entry is the hero wavelength; the others are evenly rotated from it.
+#[derive(Debug, Clone, Copy)]
+pub struct SampledWavelengths {
+    pub lambda: [f64; N_WAVELENGTHS],
+    pub secondary_terminated: bool,
+}
+
+impl SampledWavelengths {
+    /// Hero wavelength sampling over the visible range from a uniform `u`.
+    pub fn sample_uniform(u: f64) -> Self {
+        let range = LAMBDA_MAX - LAMBDA_MIN;
+        let hero = LAMBDA_MIN + u * range;
+        let mut lambda = [0.0; N_WAVELENGTHS];
+        for (i, l) in lambda.iter_mut().enumerate() {
+            let shifted = hero + i as f64 * range / N_WAVELENGTHS as f64;
+            *l = if shifted > LAMBDA_MAX { shifted - range } else { shifted };
+        }
+        SampledWavelengths {
+            lambda,
+            secondary_terminated: false,
+        }
+    }
+
+    pub fn hero(&self) -> f64 {
+        self.lambda[0]
+    }
+
+    /// Keep only the hero wavelength, e.g. after a dispersive refraction
+    /// sent each wavelength in a different direction.
+    pub fn terminate_secondary(&self) -> Self {
+        SampledWavelengths {
+            lambda: self.lambda,
+            secondary_terminated: true,
+        }
+    }
+}
+
+/// Spectral values at a set of `SampledWavelengths`.
+#[derive(Debug, Clone, Copy)]
+pub struct SampledSpectrum {
+    pub values: [f64; N_WAVELENGTHS],
+}
+
+impl SampledSpectrum {
+    pub fn new(value: f64) -> Self {
+        SampledSpectrum {
+            values: [value; N_WAVELENGTHS],
+        }
+    }
+
+    /// Upsample an RGB reflectance to the given wavelengths with Smits'
+    /// method. Linear in the input, so it also works for colors above 1.0.
+    pub fn from_rgb(color: &Color, wavelengths: &SampledWavelengths) -> Self {
+        let mut values = [0.0; N_WAVELENGTHS];
+        for (value, lambda) in values.iter_mut().zip(wavelengths.lambda.iter()) {
+            *value = smits(color, *lambda);
+        }
+        SampledSpectrum { values }
+    }
+
+    pub fn add(&self, other: &SampledSpectrum) -> Self {
+        let mut values = self.values;
+        for (value, o) in values.iter_mut().zip(other.values.iter()) {
+            *value += o;
+        }
+        SampledSpectrum { values }
+    }
+
+    pub fn mul(&self, other: &SampledSpectrum) -> Self {
+        let mut values = self.values;
+        for (value, o) in values.iter_mut().zip(other.values.iter()) {
+            *value *= o;
+        }
+        SampledSpectrum { values }
+    }
+
+    /// Move the whole estimate onto the hero wavelength, matching
+    /// `SampledWavelengths::terminate_secondary`.
+    pub fn terminate_secondary(&self) -> Self {
+        let mut values = [0.0; N_WAVELENGTHS];
+        values[0] = self.values[0] * N_WAVELENGTHS as f64;
+        SampledSpectrum { values }
+    }
+
+    /// Monte Carlo estimate of the CIE XYZ integral of this spectrum.
+    pub fn to_xyz(self, wavelengths: &SampledWavelengths) -> Vector3 {
+        let pdf = 1.0 / (LAMBDA_MAX - LAMBDA_MIN);
+        let mut xyz = Vector3::default();
+        for (value, lambda) in self.values.iter().zip(wavelengths.lambda.iter()) {
+            xyz = xyz.add(&cie_xyz(*lambda).mul(value / pdf));
+        }
+        xyz.div(N_WAVELENGTHS as f64)
+    }
+
+    /// Convert to linear sRGB, white balanced so that a constant spectrum
+    /// of 1.0 maps to RGB white. Out of gamut components are clipped to 0.
+    pub fn to_color(self, wavelengths: &SampledWavelengths) -> Color {
+        let rgb = xyz_to_linear_srgb(&self.to_xyz(wavelengths));
+        let white = white_point();
+        Color::new(
+            (rgb.x / white.x).max(0.0),
+            (rgb.y / white.y).max(0.0),
+            (rgb.z / white.z).max(0.0),
+        )
+    }
+}
+
+fn piecewise_gaussian(x: f64, mu: f64, sigma_low: f64, sigma_high: f64) -> f64 {
+    let sigma = if x < mu { sigma_low } else { sigma_high };
+    let t = (x - mu) / sigma;
+    (-0.5 * t * t).exp()
+}
+
+/// CIE 1931 2° colour matching functions, multi-lobe fit by Wyman et al.
+pub fn cie_xyz(lambda: f64) -> Vector3 {
+    let x = 1.056 * piecewise_gaussian(lambda, 599.8, 37.9, 31.0)
+        + 0.362 * piecewise_gaussian(lambda, 442.0, 16.0, 26.7)
+        - 0.065 * piecewise_gaussian(lambda, 501.1, 20.4, 26.2);
+    let y = 0.821 * piecewise_gaussian(lambda, 568.8, 46.9, 40.5)
+        + 0.286 * piecewise_gaussian(lambda, 530.9, 16.3, 31.1);
+    let z = 1.217 * piecewise_gaussian(lambda, 437.0, 11.8, 36.0)
+        + 0.681 * piecewise_gaussian(lambda, 459.0, 26.0, 13.8);
+    Vector3::new(x, y, z)
+}
+
+pub fn xyz_to_linear_srgb(xyz: &Vector3) -> Vector3 {
+    Vector3::new(
+        3.2404542 * xyz.x - 1.5371385 * xyz.y - 0.4985314 * xyz.z,
+        -0.9692660 * xyz.x + 1.8760108 * xyz.y + 0.0415560 * xyz.z,
+        0.0556434 * xyz.x - 0.2040259 * xyz.y + 1.0572252 * xyz.z,
+    )
+}
+
+/// Linear sRGB of the equal energy spectrum, integrated once at 1 nm steps.
+fn white_point() -> &'static Vector3 {
+    static WHITE: OnceLock<Vector3> = OnceLock::new();
+    WHITE.get_or_init(|| {
+        let mut xyz = Vector3::default();
+        let mut lambda = LAMBDA_MIN;
+        while lambda <= LAMBDA_MAX {
+            xyz = xyz.add(&cie_xyz(lambda));
+            lambda += 1.0;
+        }
+        xyz_to_linear_srgb(&xyz)
+    })
+}
+
+/// Spectral radiance of a black body (Planck's law), with `lambda` in
+/// nanometres and `temperature` in kelvin.
+pub fn planck(lambda: f64, temperature: f64) -> f64 {
+    const C: f64 = 299_792_458.0;
+    const H: f64 = 6.626_070_15e-34;
+    const KB: f64 = 1.380_649e-23;
+    let l = lambda * 1e-9;
+    2.0 * H * C * C / (l.powi(5) * ((H * C / (l * KB * temperature)).exp() - 1.0))
+}
+
+const BLACKBODY_MIN: f64 = 500.0;
+const BLACKBODY_MAX: f64 = 12000.0;
+const BLACKBODY_STEP: f64 = 100.0;
+
+/// Linear sRGB color of a black body at `temperature` kelvin. The color is
+/// normalized to unit luminance, then scaled by the radiant exitance relative
+/// to a 6500 K body, so hotter regions of a flame are also brighter.
+pub fn blackbody(temperature: f64) -> Color {
+    static TABLE: OnceLock<Vec<Vector3>> = OnceLock::new();
+    let table = TABLE.get_or_init(|| {
+        let mut table = Vec::new();
+        let mut t = BLACKBODY_MIN;
+        while t <= BLACKBODY_MAX {
+            let mut xyz = Vector3::default();
+            let mut lambda = LAMBDA_MIN;
+            while lambda <= LAMBDA_MAX {
+                xyz = xyz.add(&cie_xyz(lambda).mul(planck(lambda, t)));
+                lambda += 5.0;
+            }
+            let rgb = xyz_to_linear_srgb(&xyz.div(xyz.y));
+            let white = white_point();
+            let exitance = (t / 6500.0).powi(4);
+            table.push(Vector3::new(
+                (rgb.x / white.x).max(0.0) * exitance,
+                (rgb.y / white.y).max(0.0) * exitance,
+                (rgb.z / white.z).max(0.0) * exitance,
+            ));
+            t += BLACKBODY_STEP;
+        }
+        table
+    });
+
+    if temperature < BLACKBODY_MIN {
+        return Color::new(0.0, 0.0, 0.0);
+    }
+    let position = (temperature.min(BLACKBODY_MAX) - BLACKBODY_MIN) / BLACKBODY_STEP;
+    let i = (position as usize).min(table.len() - 2);
+    let f = (position - i as f64).min(1.0);
+    table[i].mul(1.0 - f).add(&table[i + 1].mul(f)).to_color()
+}
+
+const SMITS_LAMBDA_MIN: f64 = 380.0;
+const SMITS_LAMBDA_MAX: f64 = 720.0;
+const SMITS_WHITE: [f64; 10] = [1.0000, 1.0000, 0.9999, 0.9993, 0.9992, 0.9998, 1.0000, 1.0000, 1.0000, 1.0000];
+const SMITS_CYAN: [f64; 10] = [0.9710, 0.9426, 1.0007, 1.0007, 1.0007, 1.0007, 0.1564, 0.0000, 0.0000, 0.0000];
+const SMITS_MAGENTA: [f64; 10] = [1.0000, 1.0000, 0.9685, 0.2229, 0.0000, 0.0458, 0.8369, 1.0000, 1.0000, 0.9959];
+const SMITS_YELLOW: [f64; 10] = [0.0001, 0.0000, 0.1088, 0.6651, 1.0000, 1.0000, 0.9996, 0.9586, 0.9685, 0.9840];
+const SMITS_RED: [f64; 10] = [0.1012, 0.0515, 0.0000, 0.0000, 0.0000, 0.0000, 0.8325, 1.0149, 1.0149, 1.0149];
+const SMITS_GREEN: [f64; 10] = [0.0000, 0.0000, 0.0273, 0.7937, 1.0000, 0.9418, 0.1719, 0.0000, 0.0000, 0.0025];
+const SMITS_BLUE: [f64; 10] = [1.0000, 1.0000, 0.8916, 0.3323, 0.0000, 0.0000, 0.0003, 0.0369, 0.0483, 0.0496];
+
+/// Smits (1999) RGB to reflectance spectrum conversion evaluated at `lambda`.
+fn smits(color: &Color, lambda: f64) -> f64 {
+    let bins = SMITS_WHITE.len();
+    let t = (lambda - SMITS_LAMBDA_MIN) / (SMITS_LAMBDA_MAX - SMITS_LAMBDA_MIN);
+    let bin = ((t * bins as f64).max(0.0) as usize).min(bins - 1);
+    let (r, g, b) = (color.r(), color.g(), color.b());
+
+    if r <= g && r <= b {
+        let base = r * SMITS_WHITE[bin];
+        if g <= b {
+            base + (g - r) * SMITS_CYAN[bin] + (b - g) * SMITS_BLUE[bin]
+        } else {
+            base + (b - r) * SMITS_CYAN[bin] + (g - b) * SMITS_GREEN[bin]
+        }
+    } else if g <= r && g <= b {
+        let base = g * SMITS_WHITE[bin];
+        if r <= b {
+            base + (r - g) * SMITS_MAGENTA[bin] + (b - r) * SMITS_BLUE[bin]
+        } else {
+            base + (b - g) * SMITS_MAGENTA[bin] + (r - b) * SMITS_RED[bin]
+        }
+    } else {
+        let base = b * SMITS_WHITE[bin];
+        if r <= g {
+            base + (r - b) * SMITS_YELLOW[bin] + (g - r) * SMITS_GREEN[bin]
+        } else {
+            base + (g - b) * SMITS_YELLOW[bin] + (r - g) * SMITS_RED[bin]
+        }
+    }
+}
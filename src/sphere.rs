@@ -1,7 +1,10 @@
+use std::f64::consts::PI;
+use crate::aabb::Aabb;
 use crate::assert_near_eq;
 use crate::hittable::{HitRecord, Hittable};
 use crate::ray::Ray;
-use crate::vector3::Point3;
+use crate::onb::Onb;
+use crate::vector3::{Point3, Vector3};
 use crate::material::Material;
 
 pub struct Sphere {
@@ -22,16 +25,46 @@ impl Sphere {
         t > ray_tmin && t < ray_tmax
     }
 
-    fn get_hit_record(&self, ray: &Ray, t: f64) -> HitRecord {
+    fn get_hit_record(&self, ray: &Ray, t: f64) -> HitRecord<'_> {
         let point = ray.at(t);
         assert_near_eq!(point.sub(&self.center).length(), self.radius);
         let outward_normal = point.sub(&self.center).div(self.radius);
-        HitRecord::new(point, outward_normal, t, ray.direction, &*self.material)
+        let (u, v) = Sphere::get_uv(&outward_normal);
+        let (tangent, bitangent) = self.get_tangents(&outward_normal);
+        HitRecord::new(point, outward_normal, t, u, v, ray.direction, &*self.material)
+            .with_tangents(tangent, bitangent)
+    }
+
+    /// Derivatives of the surface point along `u` and `v`, given the unit
+    /// normal. Near the poles, where `v` degenerates, the frame is completed
+    /// from the `u` direction.
+    fn get_tangents(&self, normal: &Vector3) -> (Vector3, Vector3) {
+        let sin_theta = (1.0 - normal.y * normal.y).max(0.0).sqrt();
+        let dpdu = Vector3::new(normal.z, 0.0, -normal.x).mul(2.0 * PI * self.radius);
+        if sin_theta < 1e-8 {
+            let onb = Onb::new(normal);
+            return (onb.u, onb.v);
+        }
+        let dpdv = Vector3::new(
+            -normal.x * normal.y / sin_theta,
+            sin_theta,
+            -normal.z * normal.y / sin_theta,
+        )
+        .mul(PI * self.radius);
+        (dpdu, dpdv)
+    }
+
+    /// Spherical texture coordinates of a point on the unit sphere.
+    /// `u` wraps around the y axis starting at -x, `v` goes from -y to +y.
+    fn get_uv(point: &Point3) -> (f64, f64) {
+        let theta = (-point.y).clamp(-1.0, 1.0).acos();
+        let phi = (-point.z).atan2(point.x) + PI;
+        (phi / (2.0 * PI), theta / PI)
     }
 }
 
 impl Hittable for Sphere {
-    fn hit(&self, ray: &Ray, ray_tmin: f64, ray_tmax: f64) -> Option<HitRecord> {
+    fn hit(&self, ray: &Ray, ray_tmin: f64, ray_tmax: f64) -> Option<HitRecord<'_>> {
         let oc = self.center.sub(&ray.origin);
         let a = ray.direction.dot(&ray.direction);
         let h = ray.direction.dot(&oc);
@@ -55,4 +88,9 @@ impl Hittable for Sphere {
 
         None
     }
+
+    fn bounding_box(&self) -> Aabb {
+        let extent = Vector3::new(self.radius, self.radius, self.radius);
+        Aabb::new(self.center.sub(&extent), self.center.add(&extent))
+    }
 }
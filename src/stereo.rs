@@ -0,0 +1,90 @@
+use crate::vector3::Vector3;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Eye {
+    Left,
+    Right,
+}
+
+/// How the two eye images are packed into the output image.
+#[derive(Debug, Clone, Copy)]
+pub enum StereoLayout {
+    /// Left eye on the left half, right eye on the right half.
+    SideBySide,
+    /// Left eye on the top half, right eye on the bottom half.
+    TopBottom,
+}
+
+/// Stereo pair rendered from one camera. Planar projections use off-axis
+/// frustums that converge at `convergence_distance`; panoramic projections
+/// use omni-directional stereo, offsetting every ray tangentially on a
+/// circle of the interocular diameter.
+#[derive(Debug, Clone, Copy)]
+pub struct Stereo {
+    pub interocular_distance: f64,
+    pub convergence_distance: f64,
+    pub layout: StereoLayout,
+}
+
+impl Stereo {
+    pub fn new(interocular_distance: f64, convergence_distance: f64, layout: StereoLayout) -> Self {
+        if convergence_distance <= 0.0 {
+            panic!("Convergence distance must be positive");
+        }
+        Stereo {
+            interocular_distance,
+            convergence_distance,
+            layout,
+        }
+    }
+
+    /// Signed offset of the eye along the camera's right axis.
+    pub fn eye_offset(&self, eye: Eye) -> f64 {
+        match eye {
+            Eye::Left => -0.5 * self.interocular_distance,
+            Eye::Right => 0.5 * self.interocular_distance,
+        }
+    }
+
+    /// Size of the packed image for eye images of `width` by `height`.
+    pub fn output_size(&self, width: u32, height: u32) -> (u32, u32) {
+        match self.layout {
+            StereoLayout::SideBySide => (2 * width, height),
+            StereoLayout::TopBottom => (width, 2 * height),
+        }
+    }
+
+    /// Regions (`x0, y0, x1, y1`, exclusive upper bounds) of the left and
+    /// right eye images in the packed image.
+    pub fn regions(&self, width: u32, height: u32) -> [(u32, u32, u32, u32); 2] {
+        match self.layout {
+            StereoLayout::SideBySide => [(0, 0, width, height), (width, 0, 2 * width, height)],
+            StereoLayout::TopBottom => [(0, 0, width, height), (0, height, width, 2 * height)],
+        }
+    }
+
+    /// Eye and eye-image pixel for pixel `(i, j)` of the packed image.
+    pub fn locate(&self, i: u32, j: u32, width: u32, height: u32) -> (Eye, u32, u32) {
+        match self.layout {
+            StereoLayout::SideBySide if i >= width => (Eye::Right, i - width, j),
+            StereoLayout::TopBottom if j >= height => (Eye::Right, i, j - height),
+            _ => (Eye::Left, i, j),
+        }
+    }
+
+    /// Omni-directional stereo ray for a camera space direction: the origin
+    /// moves sideways to the eye's position on the viewing circle and the
+    /// direction toes in to meet the centre ray at the convergence distance.
+    /// Returns the origin and direction, both in camera space.
+    pub fn omnidirectional(&self, eye: Eye, direction: &Vector3) -> (Vector3, Vector3) {
+        let horizontal = Vector3::new(direction.x, 0.0, direction.z);
+        let length = horizontal.length();
+        if length < 1e-8 {
+            return (Vector3::default(), *direction);
+        }
+        let right = Vector3::new(horizontal.z, 0.0, -horizontal.x).div(length);
+        let origin = right.mul(self.eye_offset(eye));
+        let target = direction.normalize().mul(self.convergence_distance);
+        (origin, target.sub(&origin))
+    }
+}
@@ -0,0 +1,122 @@
+use crate::color::Color;
+use crate::material::Material;
+use crate::microfacet::fresnel_dielectric;
+use crate::lpe::Lobe;
+use crate::ray::Ray;
+use crate::vector3::Vector3;
+use crate::hittable::HitRecord;
+use crate::sampler;
+
+/// Upper bound on the interactions of one random walk. Walks that have not
+/// left the surface by then are treated as absorbed.
+const MAX_WALK_STEPS: u32 = 1024;
+
+/// Translucent material for skin, wax, marble or milk. Light refracts into
+/// the closed surface and performs a volumetric random walk until it leaves.
+///
+/// The walk starts when a ray that refracted inside reaches the surface
+/// again, and runs within `scatter` against the object's own surface, so
+/// however many steps it takes it costs the path a single bounce.
+pub struct Subsurface {
+    /// Single scattering albedo of the interior medium.
+    pub albedo: Color,
+    /// Mean distance between interactions, per RGB channel.
+    pub mean_free_path: Vector3,
+    pub refraction_index: f64,
+}
+
+impl Subsurface {
+    pub fn new(albedo: Color, mean_free_path: Vector3, refraction_index: f64) -> Self {
+        if mean_free_path.x <= 0.0 || mean_free_path.y <= 0.0 || mean_free_path.z <= 0.0 {
+            panic!("Mean free path must be positive");
+        }
+        Subsurface {
+            albedo,
+            mean_free_path,
+            refraction_index,
+        }
+    }
+
+    fn extinction(&self) -> [f64; 3] {
+        [
+            1.0 / self.mean_free_path.x,
+            1.0 / self.mean_free_path.y,
+            1.0 / self.mean_free_path.z,
+        ]
+    }
+
+    /// Reflect or refract at the smooth boundary, picking by exact Fresnel.
+    fn interface(&self, unit_direction: &Vector3, hit_record: &HitRecord) -> Vector3 {
+        let refraction_ratio = if hit_record.front_face {
+            1.0 / self.refraction_index
+        } else {
+            self.refraction_index
+        };
+        let cos_theta = unit_direction.neg().dot(&hit_record.normal);
+        if sampler::get_1d() < fresnel_dielectric(cos_theta, refraction_ratio) {
+            Vector3::reflect(unit_direction, &hit_record.normal)
+        } else {
+            Vector3::refract(unit_direction, &hit_record.normal, refraction_ratio)
+        }
+    }
+}
+
+impl Material for Subsurface {
+    fn scatter(&self, ray_in: &Ray, hit_record: &HitRecord) -> Option<(Color, Ray)> {
+        let unit_direction = ray_in.direction.normalize();
+        if hit_record.front_face {
+            let direction = self.interface(&unit_direction, hit_record);
+            let lobe = Lobe::specular_or_transmission(&direction, &hit_record.normal);
+            return Some((Color::new(1.0, 1.0, 1.0), Ray::new(hit_record.point, direction).with_lobe(lobe)));
+        }
+
+        let object = hit_record.object?;
+        let extinction = self.extinction();
+        let albedo = [self.albedo.r(), self.albedo.g(), self.albedo.b()];
+        let mut weight = [1.0; 3];
+        let mut origin = ray_in.origin;
+        let mut direction = unit_direction;
+        let mut boundary_distance = hit_record.t * ray_in.direction.length();
+        let mut exit_record: Option<HitRecord> = None;
+        let mut scattered_inside = false;
+        for _ in 0..MAX_WALK_STEPS {
+            // Chromatic free-flight sampling: pick a channel uniformly and use
+            // the average of the per-channel pdfs so every channel stays
+            // unbiased.
+            let (u1, u2) = sampler::get_2d();
+            let channel = ((u1 * 3.0) as usize).min(2);
+            let distance = -(1.0 - u2).ln() / extinction[channel];
+
+            if distance < boundary_distance {
+                let transmittance = extinction.map(|sigma_t| (-sigma_t * distance).exp());
+                let pdf = (0..3).map(|k| extinction[k] * transmittance[k]).sum::<f64>() / 3.0;
+                for k in 0..3 {
+                    weight[k] *= albedo[k] * extinction[k] * transmittance[k] / pdf;
+                }
+                origin = origin.add(&direction.mul(distance));
+                direction = Vector3::random_unit_vector();
+                scattered_inside = true;
+            } else {
+                let transmittance = extinction.map(|sigma_t| (-sigma_t * boundary_distance).exp());
+                let pdf = transmittance.iter().sum::<f64>() / 3.0;
+                for k in 0..3 {
+                    weight[k] *= transmittance[k] / pdf;
+                }
+                // The normal of a hit from inside points back in, so only a
+                // refracted direction leaves against it.
+                let boundary = exit_record.as_ref().unwrap_or(hit_record);
+                let next_direction = self.interface(&direction, boundary).normalize();
+                if next_direction.dot(&boundary.normal) <= 0.0 {
+                    let lobe = if scattered_inside { Lobe::Volume } else { Lobe::Transmission };
+                    let scattered = Ray::new(boundary.point, next_direction).with_lobe(lobe);
+                    return Some((Color::new(weight[0], weight[1], weight[2]), scattered));
+                }
+                origin = boundary.point;
+                direction = next_direction;
+            }
+            exit_record = object.hit(&Ray::new(origin, direction), 0.001, f64::INFINITY);
+            boundary_distance = exit_record.as_ref()?.t;
+        }
+        None
+    }
+}
@@ -0,0 +1,140 @@
+use std::fs;
+use std::io::{Error, ErrorKind};
+use crate::color::Color;
+use crate::vector3::Point3;
+
+/// A spatially varying color looked up at a surface point.
+/// Scalar material parameters read the red channel of their texture.
+pub trait Texture {
+    fn value(&self, u: f64, v: f64, point: &Point3) -> Color;
+}
+
+pub struct SolidColor {
+    albedo: Color,
+}
+
+impl SolidColor {
+    pub fn new(albedo: Color) -> Self {
+        SolidColor { albedo }
+    }
+
+    /// A constant texture for scalar parameters such as roughness.
+    pub fn scalar(value: f64) -> Self {
+        SolidColor::new(Color::new(value, value, value))
+    }
+}
+
+impl Texture for SolidColor {
+    fn value(&self, _u: f64, _v: f64, _point: &Point3) -> Color {
+        self.albedo
+    }
+}
+
+/// Solid 3D checker pattern alternating between two textures.
+pub struct CheckerTexture {
+    inverse_scale: f64,
+    even: Box<dyn Texture>,
+    odd: Box<dyn Texture>,
+}
+
+impl CheckerTexture {
+    pub fn new(scale: f64, even: Box<dyn Texture>, odd: Box<dyn Texture>) -> Self {
+        CheckerTexture {
+            inverse_scale: 1.0 / scale,
+            even,
+            odd,
+        }
+    }
+}
+
+impl Texture for CheckerTexture {
+    fn value(&self, u: f64, v: f64, point: &Point3) -> Color {
+        let x = (self.inverse_scale * point.x).floor() as i64;
+        let y = (self.inverse_scale * point.y).floor() as i64;
+        let z = (self.inverse_scale * point.z).floor() as i64;
+        if (x + y + z) % 2 == 0 {
+            self.even.value(u, v, point)
+        } else {
+            self.odd.value(u, v, point)
+        }
+    }
+}
+
+/// Texture read from a binary (P6) or ASCII (P3) PPM image. Values are
+/// used as stored, which is what normal and height maps expect.
+#[derive(Debug)]
+pub struct ImageTexture {
+    width: usize,
+    height: usize,
+    pixels: Vec<Color>,
+}
+
+impl ImageTexture {
+    pub fn load(path: &str) -> Result<Self, Error> {
+        let bytes = fs::read(path)?;
+        let invalid = |message: &str| Error::new(ErrorKind::InvalidData, format!("{}: {}", path, message));
+
+        // Header: magic, width, height and maximum value, separated by
+        // whitespace and optionally interleaved with comments.
+        let mut position = 0;
+        let mut header = Vec::new();
+        while header.len() < 4 {
+            while position < bytes.len() && bytes[position].is_ascii_whitespace() {
+                position += 1;
+            }
+            if position < bytes.len() && bytes[position] == b'#' {
+                while position < bytes.len() && bytes[position] != b'\n' {
+                    position += 1;
+                }
+                continue;
+            }
+            let start = position;
+            while position < bytes.len() && !bytes[position].is_ascii_whitespace() {
+                position += 1;
+            }
+            if start == position {
+                return Err(invalid("truncated header"));
+            }
+            header.push(String::from_utf8_lossy(&bytes[start..position]).into_owned());
+        }
+        let parse = |token: &str| token.parse::<usize>().map_err(|_| invalid("invalid header"));
+        let (width, height, max_value) = (parse(&header[1])?, parse(&header[2])?, parse(&header[3])?);
+        if width == 0 || height == 0 || max_value == 0 || max_value > 255 {
+            return Err(invalid("unsupported image dimensions or depth"));
+        }
+
+        let samples: Vec<u8> = match header[0].as_str() {
+            "P6" => bytes.get(position + 1..).unwrap_or_default().to_vec(),
+            "P3" => String::from_utf8_lossy(&bytes[position..])
+                .split_whitespace()
+                .map(|token| token.parse::<u8>().map_err(|_| invalid("invalid sample")))
+                .collect::<Result<_, _>>()?,
+            _ => return Err(invalid("not a PPM image")),
+        };
+        if samples.len() < width * height * 3 {
+            return Err(invalid("truncated pixel data"));
+        }
+
+        let scale = 1.0 / max_value as f64;
+        let pixels = samples
+            .chunks_exact(3)
+            .take(width * height)
+            .map(|rgb| Color::new(rgb[0] as f64 * scale, rgb[1] as f64 * scale, rgb[2] as f64 * scale))
+            .collect();
+        Ok(ImageTexture { width, height, pixels })
+    }
+
+    pub fn dimensions(&self) -> (usize, usize) {
+        (self.width, self.height)
+    }
+}
+
+impl Texture for ImageTexture {
+    fn value(&self, u: f64, v: f64, _point: &Point3) -> Color {
+        let u = u.clamp(0.0, 1.0);
+        let v = 1.0 - v.clamp(0.0, 1.0);
+        let i = ((u * self.width as f64) as usize).min(self.width - 1);
+        let j = ((v * self.height as f64) as usize).min(self.height - 1);
+        self.pixels[j * self.width + i]
+    }
+}
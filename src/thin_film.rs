@@ -0,0 +1,176 @@
+use std::f64::consts::PI;
+use crate::color::Color;
+use crate::ray::Ray;
+use crate::spectrum::SampledWavelengths;
+
+/// Wavelengths (in nanometres) used for the RGB channels outside spectral mode.
+pub const RGB_WAVELENGTHS: [f64; 3] = [630.0, 532.0, 465.0];
+
+#[derive(Debug, Clone, Copy)]
+struct Complex {
+    re: f64,
+    im: f64,
+}
+
+impl Complex {
+    fn new(re: f64, im: f64) -> Self {
+        Complex { re, im }
+    }
+
+    fn real(re: f64) -> Self {
+        Complex::new(re, 0.0)
+    }
+
+    fn add(&self, other: &Complex) -> Self {
+        Complex::new(self.re + other.re, self.im + other.im)
+    }
+
+    fn sub(&self, other: &Complex) -> Self {
+        Complex::new(self.re - other.re, self.im - other.im)
+    }
+
+    fn mul(&self, other: &Complex) -> Self {
+        Complex::new(
+            self.re * other.re - self.im * other.im,
+            self.re * other.im + self.im * other.re,
+        )
+    }
+
+    fn div(&self, other: &Complex) -> Self {
+        let denom = other.norm_squared();
+        Complex::new(
+            (self.re * other.re + self.im * other.im) / denom,
+            (self.im * other.re - self.re * other.im) / denom,
+        )
+    }
+
+    fn norm_squared(&self) -> f64 {
+        self.re * self.re + self.im * self.im
+    }
+
+    fn sqrt(&self) -> Self {
+        let r = self.norm_squared().sqrt();
+        let re = (0.5 * (r + self.re)).max(0.0).sqrt();
+        let im = (0.5 * (r - self.re)).max(0.0).sqrt();
+        Complex::new(re, if self.im < 0.0 { -im } else { im })
+    }
+
+    /// `e^(i z)`
+    fn exp_i(&self) -> Self {
+        let magnitude = (-self.im).exp();
+        Complex::new(magnitude * self.re.cos(), magnitude * self.re.sin())
+    }
+}
+
+/// What lies beneath a thin film.
+#[derive(Debug, Clone, Copy)]
+pub enum Substrate {
+    Dielectric(f64),
+    /// Complex index of refraction `n + ik` of a metal.
+    Conductor { n: f64, k: f64 },
+}
+
+impl Substrate {
+    /// Conductor matching a normal incidence reflectivity and edge tint,
+    /// using Gulbrandsen's artist friendly parameterization.
+    pub fn from_reflectivity(reflectivity: f64, edge_tint: f64) -> Self {
+        let r = reflectivity.clamp(0.0, 0.99);
+        let g = edge_tint.clamp(0.0, 1.0);
+        let sqrt_r = r.sqrt();
+        let n = g * (1.0 - r) / (1.0 + r) + (1.0 - g) * (1.0 + sqrt_r) / (1.0 - sqrt_r);
+        let k2 = (r * (n + 1.0) * (n + 1.0) - (n - 1.0) * (n - 1.0)) / (1.0 - r);
+        Substrate::Conductor { n, k: k2.max(0.0).sqrt() }
+    }
+
+    fn refraction_index(&self) -> Complex {
+        match *self {
+            Substrate::Dielectric(n) => Complex::real(n),
+            Substrate::Conductor { n, k } => Complex::new(n, k),
+        }
+    }
+}
+
+/// Thin dielectric layer whose interference tints reflections, as seen on
+/// soap bubbles, oil slicks and anti-reflection coated lenses.
+#[derive(Debug, Clone, Copy)]
+pub struct ThinFilm {
+    /// Film thickness in nanometres.
+    pub thickness: f64,
+    pub refraction_index: f64,
+}
+
+impl ThinFilm {
+    pub fn new(thickness: f64, refraction_index: f64) -> Self {
+        if thickness < 0.0 {
+            panic!("Film thickness must not be negative");
+        }
+        ThinFilm {
+            thickness,
+            refraction_index,
+        }
+    }
+
+    /// Airy reflectance of unpolarized light at `lambda` nanometres arriving
+    /// from a medium of index `n1` onto the film over `substrate`.
+    pub fn reflectance(&self, cos_theta_i: f64, n1: f64, substrate: &Substrate, lambda: f64) -> f64 {
+        let cos1 = Complex::real(cos_theta_i.clamp(0.0, 1.0));
+        let sin2 = 1.0 - cos_theta_i * cos_theta_i;
+        let eta1 = Complex::real(n1);
+        let eta2 = Complex::real(self.refraction_index);
+        let eta3 = substrate.refraction_index();
+        let cos_in = |eta: &Complex| {
+            let ratio = eta1.div(eta);
+            Complex::real(1.0).sub(&ratio.mul(&ratio).mul(&Complex::real(sin2))).sqrt()
+        };
+        let cos2 = cos_in(&eta2);
+        let cos3 = cos_in(&eta3);
+
+        let phase = Complex::real(4.0 * PI * self.thickness / lambda)
+            .mul(&eta2)
+            .mul(&cos2)
+            .exp_i();
+        let airy = |r12: Complex, r23: Complex| {
+            let numerator = r12.add(&r23.mul(&phase));
+            let denominator = Complex::real(1.0).add(&r12.mul(&r23).mul(&phase));
+            numerator.div(&denominator).norm_squared()
+        };
+        let r_s = |ea: &Complex, ca: &Complex, eb: &Complex, cb: &Complex| {
+            ea.mul(ca).sub(&eb.mul(cb)).div(&ea.mul(ca).add(&eb.mul(cb)))
+        };
+        let r_p = |ea: &Complex, ca: &Complex, eb: &Complex, cb: &Complex| {
+            eb.mul(ca).sub(&ea.mul(cb)).div(&eb.mul(ca).add(&ea.mul(cb)))
+        };
+
+        let s = airy(r_s(&eta1, &cos1, &eta2, &cos2), r_s(&eta2, &cos2, &eta3, &cos3));
+        let p = airy(r_p(&eta1, &cos1, &eta2, &cos2), r_p(&eta2, &cos2, &eta3, &cos3));
+        (0.5 * (s + p)).clamp(0.0, 1.0)
+    }
+
+    /// Reflectance as a color for the given ray. Spectral rays are evaluated
+    /// at their hero wavelength only, so the returned wavelengths have their
+    /// secondaries terminated; RGB rays use `RGB_WAVELENGTHS`.
+    /// `substrate` gives the substrate at a wavelength in nanometres.
+    pub fn reflectance_for<F>(
+        &self,
+        ray_in: &Ray,
+        cos_theta_i: f64,
+        n1: f64,
+        substrate: F,
+    ) -> (Color, Option<SampledWavelengths>)
+    where
+        F: Fn(f64) -> Substrate,
+    {
+        match ray_in.wavelengths {
+            Some(wavelengths) => {
+                let lambda = wavelengths.hero();
+                let r = self.reflectance(cos_theta_i, n1, &substrate(lambda), lambda);
+                (Color::new(r, r, r), Some(wavelengths.terminate_secondary()))
+            }
+            None => {
+                let [r, g, b] = RGB_WAVELENGTHS
+                    .map(|lambda| self.reflectance(cos_theta_i, n1, &substrate(lambda), lambda));
+                (Color::new(r, g, b), None)
+            }
+        }
+    }
+}
@@ -0,0 +1,163 @@
+use crate::blue_noise;
+use crate::color::{linear_to_srgb, Color};
+
+/// Curve compressing scene-linear radiance into the displayable range.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ToneMapper {
+    /// Clip every channel at 1.0.
+    Clip,
+    /// Extended Reinhard on luminance, mapping `white` to 1.0.
+    Reinhard { white: f64 },
+    /// Stephen Hill's fit of the ACES reference rendering and sRGB output
+    /// transforms.
+    AcesFilmic,
+    /// Troy Sobotka's AgX with its default look, which desaturates bright
+    /// colors towards white instead of skewing their hue.
+    AgX,
+    /// John Hable's filmic curve from Uncharted 2.
+    Hable,
+}
+
+impl ToneMapper {
+    /// Display-linear color in [0, 1] for scene-linear `color`.
+    pub fn apply(&self, color: &Color) -> Color {
+        let rgb = [color.r(), color.g(), color.b()];
+        let mapped = match self {
+            ToneMapper::Clip => rgb,
+            ToneMapper::Reinhard { white } => {
+                let luminance = color.luminance();
+                if luminance <= 0.0 {
+                    rgb
+                } else {
+                    let mapped = luminance * (1.0 + luminance / (white * white)) / (1.0 + luminance);
+                    rgb.map(|value| value * mapped / luminance)
+                }
+            }
+            ToneMapper::AcesFilmic => {
+                let v = multiply(&ACES_INPUT, rgb).map(|v| (v * (v + 0.0245786) - 0.000090537) / (v * (0.983729 * v + 0.4329510) + 0.238081));
+                multiply(&ACES_OUTPUT, v)
+            }
+            ToneMapper::AgX => {
+                let v = multiply(&AGX_INSET, rgb).map(|v| {
+                    let ev = v.max(1e-10).log2().clamp(AGX_MIN_EV, AGX_MAX_EV);
+                    agx_contrast((ev - AGX_MIN_EV) / (AGX_MAX_EV - AGX_MIN_EV))
+                });
+                // The curve output is display encoded; undo that so every
+                // mapper hands the same display-linear values to the OETF.
+                multiply(&AGX_OUTSET, v).map(|v| v.max(0.0).powf(2.2))
+            }
+            ToneMapper::Hable => {
+                let white_scale = 1.0 / hable(HABLE_WHITE);
+                rgb.map(|v| hable(2.0 * v) * white_scale)
+            }
+        };
+        let [r, g, b] = mapped.map(|value| value.clamp(0.0, 1.0));
+        Color::new(r, g, b)
+    }
+}
+
+/// The path from rendered radiance to 8-bit sRGB pixels.
+#[derive(Debug, Clone, Copy)]
+pub struct DisplayTransform {
+    /// Exposure adjustment in stops; each stop doubles the radiance.
+    pub exposure: f64,
+    pub tone_mapper: ToneMapper,
+    /// Add blue noise before quantizing, breaking up banding in gradients.
+    pub dither: bool,
+}
+
+impl DisplayTransform {
+    /// No exposure change, hard clipping and no dithering.
+    pub fn new() -> Self {
+        DisplayTransform {
+            exposure: 0.0,
+            tone_mapper: ToneMapper::Clip,
+            dither: false,
+        }
+    }
+
+    pub fn with_exposure(mut self, exposure: f64) -> Self {
+        self.exposure = exposure;
+        self
+    }
+
+    pub fn with_tone_mapper(mut self, tone_mapper: ToneMapper) -> Self {
+        if let ToneMapper::Reinhard { white } = tone_mapper {
+            if white <= 0.0 {
+                panic!("Reinhard white point must be positive");
+            }
+        }
+        self.tone_mapper = tone_mapper;
+        self
+    }
+
+    pub fn with_dither(mut self, dither: bool) -> Self {
+        self.dither = dither;
+        self
+    }
+
+    /// 8-bit sRGB value of `color` for the pixel at `(x, y)`.
+    pub fn encode(&self, color: &Color, x: u32, y: u32) -> [u8; 3] {
+        let exposed = color.mul(2f64.powf(self.exposure));
+        let mapped = self.tone_mapper.apply(&exposed);
+        let mut encoded = [0; 3];
+        for (channel, value) in [mapped.r(), mapped.g(), mapped.b()].into_iter().enumerate() {
+            // Rounding with a uniform offset in [0, 1) instead of 0.5 keeps
+            // the average exact. Each channel reads the tile elsewhere so
+            // their noise does not line up into gray grain.
+            let offset = if self.dither {
+                let shift = channel as u32 * (blue_noise::TILE_SIZE as u32 / 3);
+                blue_noise::value(x + shift, y + 2 * shift)
+            } else {
+                0.5
+            };
+            encoded[channel] = (linear_to_srgb(value) * 255.0 + offset).floor().min(255.0) as u8;
+        }
+        encoded
+    }
+}
+
+const ACES_INPUT: [[f64; 3]; 3] = [
+    [0.59719, 0.35458, 0.04823],
+    [0.07600, 0.90834, 0.01566],
+    [0.02840, 0.13383, 0.83777],
+];
+
+const ACES_OUTPUT: [[f64; 3]; 3] = [
+    [1.60475, -0.53108, -0.07367],
+    [-0.10208, 1.10813, -0.00605],
+    [-0.00327, -0.07276, 1.07602],
+];
+
+const AGX_INSET: [[f64; 3]; 3] = [
+    [0.842479062253094, 0.0784335999999992, 0.0792237451477643],
+    [0.0423282422610123, 0.878468636469772, 0.0791661274605434],
+    [0.0423756549057051, 0.0784336, 0.879142973793104],
+];
+
+const AGX_OUTSET: [[f64; 3]; 3] = [
+    [1.19687900512017, -0.0980208811401368, -0.0990297440797205],
+    [-0.0528968517574562, 1.15190312990417, -0.0989611768448433],
+    [-0.0529716355144438, -0.0980434501171241, 1.15107367264116],
+];
+
+const AGX_MIN_EV: f64 = -12.47393;
+const AGX_MAX_EV: f64 = 4.026069;
+
+const HABLE_WHITE: f64 = 11.2;
+
+fn multiply(matrix: &[[f64; 3]; 3], v: [f64; 3]) -> [f64; 3] {
+    matrix.map(|row| row[0] * v[0] + row[1] * v[1] + row[2] * v[2])
+}
+
+/// Polynomial fit of the AgX base contrast curve over log-encoded input.
+fn agx_contrast(x: f64) -> f64 {
+    let x2 = x * x;
+    let x4 = x2 * x2;
+    15.5 * x4 * x2 - 40.14 * x4 * x + 31.96 * x4 - 6.868 * x2 * x + 0.4298 * x2 + 0.1191 * x - 0.00232
+}
+
+fn hable(x: f64) -> f64 {
+    let (a, b, c, d, e, f) = (0.15, 0.50, 0.10, 0.20, 0.02, 0.30);
+    (x * (a * x + c * b) + d * e) / (x * (a * x + b) + d * f) - e / f
+}
@@ -1,4 +1,5 @@
 use crate::color::Color;
+use crate::vector3::Vector3;
 
 #[macro_export]
 macro_rules! assert_near_eq {
@@ -23,3 +24,20 @@ pub fn lerp(a: Color, b: Color, t: f64) -> Color {
         a.b() * (1.0 - t) + b.b() * t,
     )
 }
+
+/// Beer-Lambert transmittance through `distance` units of a medium with
+/// the given per-channel absorption coefficient.
+pub fn beer_lambert(absorption: &Vector3, distance: f64) -> Color {
+    Color::new(
+        (-absorption.x * distance).exp(),
+        (-absorption.y * distance).exp(),
+        (-absorption.z * distance).exp(),
+    )
+}
+
+/// Absorption coefficient for a medium that tints light to `color`
+/// after travelling `distance` units through it.
+pub fn absorption_from_tint(color: Color, distance: f64) -> Vector3 {
+    let coefficient = |x: f64| -x.max(1e-6).ln() / distance;
+    Vector3::new(coefficient(color.r()), coefficient(color.g()), coefficient(color.b()))
+}
@@ -1,5 +1,6 @@
 use rand::Rng;
 use crate::color::Color;
+use crate::sampler;
 
 #[derive(Debug, Default, Clone, Copy)]
 pub struct Vector3 {
@@ -83,16 +84,19 @@ impl Vector3 {
     }
 
     pub fn random_unit_vector() -> Self {
-        loop {
-            let p = Self::random(-1.0, 1.0);
-            let length = p.length();
-            if length <= 1e-160 {
-                continue;
-            }
-            if length <= 1.0 {
-                return p.div(length);
-            }
-        }
+        let (r1, r2) = sampler::get_2d();
+        let z = 1.0 - 2.0 * r1;
+        let r = (1.0 - z * z).max(0.0).sqrt();
+        let phi = 2.0 * std::f64::consts::PI * r2;
+        Self::new(phi.cos() * r, phi.sin() * r, z)
+    }
+
+    /// Cosine-weighted direction in the hemisphere around +z.
+    pub fn random_cosine_direction() -> Self {
+        let (r1, r2) = sampler::get_2d();
+        let phi = 2.0 * std::f64::consts::PI * r1;
+        let r = r2.sqrt();
+        Self::new(phi.cos() * r, phi.sin() * r, (1.0 - r2).sqrt())
     }
 
     pub fn random_in_hemisphere(normal: &Vector3) -> Self {
@@ -104,13 +108,21 @@ impl Vector3 {
         }
     }
 
+    /// Uniform point in the unit disk, by Shirley's concentric mapping so
+    /// that stratified samples stay stratified.
     pub fn random_in_unit_disk() -> Self {
-        loop {
-            let p = Self::new(rand::thread_rng().gen_range(-1.0..1.0), rand::thread_rng().gen_range(-1.0..1.0), 0.0);
-            if p.dot(&p) < 1.0 {
-                return p;
-            }
+        let (r1, r2) = sampler::get_2d();
+        let (a, b) = (2.0 * r1 - 1.0, 2.0 * r2 - 1.0);
+        if a == 0.0 && b == 0.0 {
+            return Self::new(0.0, 0.0, 0.0);
         }
+        let quarter_pi = std::f64::consts::FRAC_PI_4;
+        let (r, theta) = if a.abs() > b.abs() {
+            (a, quarter_pi * (b / a))
+        } else {
+            (b, 2.0 * quarter_pi - quarter_pi * (a / b))
+        };
+        Self::new(r * theta.cos(), r * theta.sin(), 0.0)
     }
 
     pub fn near_zero(&self) -> bool {
@@ -124,7 +136,7 @@ impl Vector3 {
 
     pub fn refract(vector: &Vector3, normal: &Vector3, etai_over_etat: f64) -> Self {
         let cos_theta = vector.neg().dot(normal).min(1.0);
-        let r_out_perpendicular = normal.mul(cos_theta).add(&vector).mul(etai_over_etat);
+        let r_out_perpendicular = normal.mul(cos_theta).add(vector).mul(etai_over_etat);
         let r_out_parallel = normal.mul(-((1.0 - r_out_perpendicular.dot(&r_out_perpendicular)).abs().sqrt()));
         r_out_perpendicular.add(&r_out_parallel)
     }
@@ -0,0 +1,83 @@
+use std::fs;
+use std::io::{Error, ErrorKind};
+use crate::vector3::Point3;
+
+/// Dense 3D grid of scalar values, stored with x varying fastest.
+pub struct VoxelGrid {
+    nx: usize,
+    ny: usize,
+    nz: usize,
+    data: Vec<f32>,
+}
+
+impl VoxelGrid {
+    pub fn new(nx: usize, ny: usize, nz: usize, data: Vec<f32>) -> Result<Self, Error> {
+        if nx == 0 || ny == 0 || nz == 0 || data.len() != nx * ny * nz {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("Expected {}x{}x{} voxels, got {}", nx, ny, nz, data.len()),
+            ));
+        }
+        Ok(VoxelGrid { nx, ny, nz, data })
+    }
+
+    /// Load a headerless file of little-endian `f32` values.
+    pub fn from_raw(path: &str, nx: usize, ny: usize, nz: usize) -> Result<Self, Error> {
+        let bytes = fs::read(path)?;
+        let data = bytes
+            .chunks_exact(4)
+            .map(|chunk| f32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+            .collect();
+        VoxelGrid::new(nx, ny, nz, data)
+    }
+
+    /// Load a dense text export: an `nx ny nz` header followed by the
+    /// whitespace separated voxel values.
+    pub fn from_dense(path: &str) -> Result<Self, Error> {
+        let text = fs::read_to_string(path)?;
+        let mut tokens = text.split_whitespace();
+        let mut dimension = || -> Result<usize, Error> {
+            tokens
+                .next()
+                .and_then(|token| token.parse().ok())
+                .ok_or_else(|| Error::new(ErrorKind::InvalidData, "Invalid grid header"))
+        };
+        let (nx, ny, nz) = (dimension()?, dimension()?, dimension()?);
+        let data = tokens
+            .map(|token| token.parse::<f32>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+        VoxelGrid::new(nx, ny, nz, data)
+    }
+
+    pub fn max_value(&self) -> f64 {
+        self.data.iter().fold(0.0f32, |max, &value| max.max(value)) as f64
+    }
+
+    fn voxel(&self, x: usize, y: usize, z: usize) -> f64 {
+        self.data[x + self.nx * (y + self.ny * z)] as f64
+    }
+
+    /// Trilinearly interpolated value at `point` in grid space, where the
+    /// grid spans `[0, 1]` on every axis. Outside the grid the value is 0.
+    pub fn lookup(&self, point: &Point3) -> f64 {
+        if !(0.0..=1.0).contains(&point.x) || !(0.0..=1.0).contains(&point.y) || !(0.0..=1.0).contains(&point.z) {
+            return 0.0;
+        }
+        let cell = |p: f64, n: usize| {
+            let scaled = p * (n - 1) as f64;
+            let i = (scaled as usize).min(n.saturating_sub(2));
+            (i, (i + 1).min(n - 1), scaled - i as f64)
+        };
+        let (x0, x1, fx) = cell(point.x, self.nx);
+        let (y0, y1, fy) = cell(point.y, self.ny);
+        let (z0, z1, fz) = cell(point.z, self.nz);
+        let lerp = |a: f64, b: f64, t: f64| a * (1.0 - t) + b * t;
+
+        let c00 = lerp(self.voxel(x0, y0, z0), self.voxel(x1, y0, z0), fx);
+        let c10 = lerp(self.voxel(x0, y1, z0), self.voxel(x1, y1, z0), fx);
+        let c01 = lerp(self.voxel(x0, y0, z1), self.voxel(x1, y0, z1), fx);
+        let c11 = lerp(self.voxel(x0, y1, z1), self.voxel(x1, y1, z1), fx);
+        lerp(lerp(c00, c10, fy), lerp(c01, c11, fy), fz)
+    }
+}